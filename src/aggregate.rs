@@ -0,0 +1,171 @@
+use crate::data_model::*;
+
+/// Combines several runs of the same design into a single profile, whose metrics are thereby
+/// weighted by time and cycles across all runs
+///
+/// The header, boards and run information are taken from the first run. The kernel invocations and
+/// memory transfers of every run are shifted in time to follow the previous run, as the timestamps
+/// of separate runs are unrelated. The profiles are moved rather than copied, a single profile is
+/// returned as it is.
+pub fn merge(mut profiles: Vec<Profile>) -> Option<Profile>
+{
+	if profiles.len() <= 1
+	{
+		return profiles.pop();
+	}
+
+	let mut profiles = profiles.into_iter();
+	let mut merged = profiles.next()?;
+
+	let mut kernels = std::mem::take(&mut merged.kernels.nodes);
+	let mut memory_transfers = std::mem::take(&mut merged.memory_transfers.nodes);
+	let mut next_start_time = None;
+
+	loop
+	{
+		let (first_start_time, last_end_time) = timespan(&kernels, &memory_transfers);
+		let start_time = *next_start_time.get_or_insert(first_start_time);
+		let shift = |time: u64| time - first_start_time + start_time;
+
+		for node in kernels.iter_mut()
+		{
+			if let Node::Kernel(kernel) = node
+			{
+				kernel.start_time = shift(kernel.start_time);
+				kernel.end_time = shift(kernel.end_time);
+
+				for sample_timestamp in kernel.sample_timestamps.iter_mut()
+				{
+					*sample_timestamp = shift(*sample_timestamp);
+				}
+			}
+		}
+
+		for node in memory_transfers.iter_mut()
+		{
+			if let Node::MemoryTransfers(memory_transfers) = node
+			{
+				memory_transfers.start_time = shift(memory_transfers.start_time);
+				memory_transfers.end_time = shift(memory_transfers.end_time);
+			}
+		}
+
+		merged.kernels.nodes.append(&mut kernels);
+		merged.memory_transfers.nodes.append(&mut memory_transfers);
+		next_start_time = Some(shift(last_end_time.max(first_start_time)));
+
+		match profiles.next()
+		{
+			Some(profile) =>
+			{
+				kernels = profile.kernels.nodes;
+				memory_transfers = profile.memory_transfers.nodes;
+			}
+			None => return Some(merged),
+		}
+	}
+}
+
+/// First start and last end of any kernel invocation or memory transfer (in ns), including sample
+/// timestamps outside of the kernel runtime, which would otherwise end up before the shifted run
+fn timespan(kernels: &[Node], memory_transfers: &[Node]) -> (u64, u64)
+{
+	kernels.iter()
+		.chain(memory_transfers.iter())
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel.sample_timestamps.iter()
+					.fold((kernel.start_time, kernel.end_time), |(start_time, end_time), &sample_timestamp|
+						(start_time.min(sample_timestamp), end_time.max(sample_timestamp)))),
+				Node::MemoryTransfers(memory_transfers) =>
+					Some((memory_transfers.start_time, memory_transfers.end_time)),
+				_ => None
+			})
+		.fold(None::<(u64, u64)>, |span, (start_time, end_time)|
+			match span
+			{
+				Some((first_start_time, last_end_time)) =>
+					Some((first_start_time.min(start_time), last_end_time.max(end_time))),
+				None => Some((start_time, end_time)),
+			})
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn profile(start_time: u64, sample_timestamps: &[u64], transfer_times: (u64, u64)) -> Profile
+	{
+		let kernel = fixtures::kernel("kernel", start_time, sample_timestamps, vec![]);
+
+		let mut profile = fixtures::profile(vec![kernel]);
+		profile["memtransfers"]["nodes"] =
+			serde_json::json!([fixtures::memory_transfer("write", 0, 0, transfer_times.0, transfer_times.1)]);
+
+		fixtures::load(profile)
+	}
+
+	fn times(profile: &Profile) -> Vec<(u64, u64, Vec<u64>)>
+	{
+		profile.kernels.nodes.iter()
+			.chain(profile.memory_transfers.nodes.iter())
+			.filter_map(|node|
+				match node
+				{
+					Node::Kernel(kernel) =>
+						Some((kernel.start_time, kernel.end_time, kernel.sample_timestamps.clone())),
+					Node::MemoryTransfers(memory_transfers) =>
+						Some((memory_transfers.start_time, memory_transfers.end_time, Vec::new())),
+					_ => None
+				})
+			.collect()
+	}
+
+	#[test]
+	fn merge_shifts_runs_one_after_another()
+	{
+		let merged = merge(vec![
+			profile(1000, &[2000, 3000, 4000], (500, 900)),
+			profile(10_100, &[10_200, 10_300], (10_000, 10_050)),
+		]).unwrap();
+
+		// The second run starts at its first memory transfer, right where the first run ends
+		assert_eq!(times(&merged), vec![
+			(1000, 4000, vec![2000, 3000, 4000]),
+			(4100, 4300, vec![4200, 4300]),
+			(500, 900, vec![]),
+			(4000, 4050, vec![]),
+		]);
+	}
+
+	#[test]
+	fn merge_keeps_a_single_run()
+	{
+		let merged = merge(vec![profile(1000, &[2000, 3000, 4000], (500, 900))]).unwrap();
+
+		assert_eq!(times(&merged), vec![(1000, 4000, vec![2000, 3000, 4000]), (500, 900, vec![])]);
+		assert!(merge(Vec::new()).is_none());
+	}
+
+	#[test]
+	fn merge_covers_sample_timestamps_outside_of_the_kernel_runtime()
+	{
+		let merged = merge(vec![
+			profile(1000, &[2000], (1500, 1600)),
+			profile(10_000, &[9000, 11_000, 12_000], (10_500, 10_600)),
+		]).unwrap();
+
+		// The second run starts at the sample before its kernel invocation
+
+		assert_eq!(times(&merged), vec![
+			(1000, 2000, vec![2000]),
+			(3000, 5000, vec![2000, 4000, 5000]),
+			(1500, 1600, vec![]),
+			(3500, 3600, vec![]),
+		]);
+	}
+}
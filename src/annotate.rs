@@ -0,0 +1,279 @@
+use crate::data_model::FileReference;
+use crate::metrics::*;
+use crate::report::*;
+
+/// Metrics shown in the gutter of an annotated line
+const GUTTER_METRICS: &[Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Bandwidth];
+
+#[derive(Debug, Clone)]
+pub struct Annotation
+{
+	pub memory_type: &'static str,
+	pub column_number: Option<u32>,
+	/// The line calls the code the module instance originates from
+	pub via_callsite: bool,
+	pub values: Vec<Option<f32>>,
+}
+
+/// Annotations per line of the source file, matched against the file references including their
+/// callsite chains
+pub fn annotations(report: &Report, source_file: &std::path::Path)
+	-> std::collections::BTreeMap<u32, Vec<Annotation>>
+{
+	let mut annotations = std::collections::BTreeMap::new();
+	let matching = Matching::new(report, source_file);
+
+	collect_annotations("global", &report.global_memory, source_file, matching, &mut annotations);
+	collect_annotations("local", &report.local_memory, source_file, matching, &mut annotations);
+	collect_annotations("channel", &report.channel, source_file, matching, &mut annotations);
+	collect_annotations("loop", &report.loops, source_file, matching, &mut annotations);
+
+	annotations
+}
+
+fn collect_annotations<Metrics>(memory_type: &'static str, groups: &[ModuleInstanceGroup<Metrics>],
+	source_file: &std::path::Path, matching: Matching,
+	annotations: &mut std::collections::BTreeMap<u32, Vec<Annotation>>)
+where
+	Metrics: MetricValues,
+{
+	for group in groups.iter()
+	{
+		let mut references = Vec::new();
+		matching_references(&group.source_files, source_file, matching, false, &mut references);
+
+		for (file_reference, via_callsite) in references.into_iter()
+		{
+			annotations.entry(file_reference.line).or_default()
+				.push(Annotation
+				{
+					memory_type,
+					column_number: file_reference.column_number,
+					via_callsite,
+					values: GUTTER_METRICS.iter().map(|&metric| group.metrics.value(metric)).collect(),
+				});
+		}
+	}
+}
+
+fn matching_references<'a>(file_references: &'a [FileReference], source_file: &std::path::Path,
+	matching: Matching, via_callsite: bool, matches: &mut Vec<(&'a FileReference, bool)>)
+{
+	for file_reference in file_references.iter()
+	{
+		if matching.refers_to(&file_reference.file_name, source_file)
+		{
+			matches.push((file_reference, via_callsite));
+		}
+
+		matching_references(&file_reference.callsite, source_file, matching, true, matches);
+	}
+}
+
+/// Profiles contain the paths of the build machine, hence the file name suffices as fallback if no
+/// referenced path ends with the source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Matching
+{
+	PathSuffix,
+	FileName,
+}
+
+impl Matching
+{
+	fn new(report: &Report, source_file: &std::path::Path) -> Matching
+	{
+		fn any_suffix(file_references: &[FileReference], source_file: &std::path::Path) -> bool
+		{
+			file_references.iter()
+				.any(|file_reference| file_reference.file_name.ends_with(source_file)
+					|| any_suffix(&file_reference.callsite, source_file))
+		}
+
+		if all_source_files(report).any(|source_files| any_suffix(source_files, source_file))
+		{
+			Matching::PathSuffix
+		}
+		else
+		{
+			Matching::FileName
+		}
+	}
+
+	fn refers_to(self, file_name: &std::path::Path, source_file: &std::path::Path) -> bool
+	{
+		match self
+		{
+			Matching::PathSuffix => file_name.ends_with(source_file),
+			Matching::FileName =>
+				file_name.file_name().is_some() && file_name.file_name() == source_file.file_name(),
+		}
+	}
+}
+
+/// Source files of every module instance group of the report
+fn all_source_files(report: &Report) -> impl Iterator<Item = &Vec<FileReference>>
+{
+	report.global_memory.iter().map(|group| &group.source_files)
+		.chain(report.local_memory.iter().map(|group| &group.source_files))
+		.chain(report.channel.iter().map(|group| &group.source_files))
+		.chain(report.loops.iter().map(|group| &group.source_files))
+}
+
+/// Finds the source file as given, at the paths referenced by the profile or in the search path
+pub fn resolve_source_file(source_file: &std::path::Path, report: &Report,
+	search_path: &[std::path::PathBuf]) -> Option<std::path::PathBuf>
+{
+	let matching = Matching::new(report, source_file);
+	let mut referenced_paths = std::collections::BTreeSet::new();
+
+	for source_files in all_source_files(report)
+	{
+		let mut references = Vec::new();
+		matching_references(source_files, source_file, matching, false, &mut references);
+
+		referenced_paths.extend(references.into_iter()
+			.map(|(file_reference, _)| file_reference.file_name.clone()));
+	}
+
+	let searched_paths = search_path.iter()
+		.flat_map(|directory|
+			std::iter::once(directory.join(source_file))
+				.chain(source_file.file_name().map(|file_name| directory.join(file_name))));
+
+	std::iter::once(source_file.to_owned())
+		.chain(referenced_paths)
+		.chain(searched_paths)
+		.find(|path| path.is_file())
+}
+
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, source: &str,
+	annotations: &std::collections::BTreeMap<u32, Vec<Annotation>>) -> std::io::Result<()>
+{
+	let gutters = annotations.iter()
+		.map(|(&line, annotations)|
+			(line, annotations.iter().map(format_annotation).collect::<Vec<_>>()))
+		.collect::<std::collections::BTreeMap<_, _>>();
+
+	let gutter_width = gutters.values().flatten()
+		.map(|gutter| gutter.chars().count())
+		.max()
+		.unwrap_or(0);
+	let line_number_width = source.lines().count().to_string().len();
+
+	for (index, text) in source.lines().enumerate()
+	{
+		let line = index as u32 + 1;
+		let mut gutter = gutters.get(&line).into_iter().flatten();
+
+		writeln!(writer, "{:<gutter_width$} │ {:>line_number_width$} │ {}",
+			gutter.next().map(String::as_str).unwrap_or_default(), line, text,
+			gutter_width = gutter_width, line_number_width = line_number_width)?;
+
+		for additional in gutter
+		{
+			writeln!(writer, "{:<gutter_width$} │ {:>line_number_width$} │", additional, "",
+				gutter_width = gutter_width, line_number_width = line_number_width)?;
+		}
+	}
+
+	let beyond_end = gutters.range(source.lines().count() as u32 + 1..);
+
+	for (line, gutter) in beyond_end
+	{
+		for annotation in gutter.iter()
+		{
+			writeln!(writer, "{:<gutter_width$} │ {:>line_number_width$} │ (beyond the end of the file)",
+				annotation, line, gutter_width = gutter_width, line_number_width = line_number_width)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn format_annotation(annotation: &Annotation) -> String
+{
+	let values = GUTTER_METRICS.iter()
+		.zip(annotation.values.iter())
+		.filter_map(|(metric, value)|
+			value.map(|value| format!("{} {:.1}{}", metric.label(), value, metric.unit())))
+		.collect::<Vec<_>>().join(", ");
+
+	format!("{}{}{}: {}",
+		annotation.memory_type,
+		annotation.column_number.map(|column| format!(" @{}", column)).unwrap_or_default(),
+		if annotation.via_callsite { " (callsite)" } else { "" },
+		values)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn report() -> Report
+	{
+		Report::new(&fixtures::load(fixtures::example()), &Settings::default())
+	}
+
+	fn lines(source_file: &str) -> Vec<(u32, &'static str, bool)>
+	{
+		annotations(&report(), std::path::Path::new(source_file)).into_iter()
+			.flat_map(|(line, annotations)|
+				annotations.into_iter()
+					.map(move |annotation| (line, annotation.memory_type, annotation.via_callsite)))
+			.collect()
+	}
+
+	#[test]
+	fn annotations_match_the_path_suffix_or_else_the_file_name()
+	{
+		assert_eq!(lines("src/kernel.cpp"), vec![(10, "global", false), (20, "loop", false)]);
+		assert_eq!(lines("/checkout/kernel.cpp"), vec![(10, "global", false), (20, "loop", false)]);
+		assert_eq!(lines("main.cpp"), vec![(3, "global", true)]);
+		assert!(lines("kernel.hpp").is_empty());
+	}
+
+	#[test]
+	fn resolve_source_file_searches_the_search_path_by_file_name()
+	{
+		let directory = std::env::temp_dir().join(format!("annotate-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		std::fs::write(directory.join("kernel.cpp"), "").unwrap();
+
+		let resolved = resolve_source_file(std::path::Path::new("src/kernel.cpp"), &report(),
+			&[std::path::PathBuf::from("/nonexistent"), directory.clone()]);
+		let unresolved = resolve_source_file(std::path::Path::new("kernel.hpp"), &report(),
+			std::slice::from_ref(&directory));
+
+		std::fs::remove_dir_all(&directory).unwrap();
+
+		assert_eq!(resolved, Some(directory.join("kernel.cpp")));
+		assert_eq!(unresolved, None);
+	}
+
+	#[test]
+	fn write_puts_the_annotations_into_the_gutter()
+	{
+		let mut annotations = std::collections::BTreeMap::new();
+		annotations.insert(2, vec![
+			Annotation { memory_type: "global", column_number: Some(5), via_callsite: false,
+				values: vec![Some(75.), None, Some(750.)] },
+			Annotation { memory_type: "loop", column_number: None, via_callsite: true,
+				values: vec![Some(50.), None, None] },
+		]);
+		annotations.insert(4, vec![Annotation { memory_type: "local", column_number: None, via_callsite: false,
+			values: vec![None, Some(1.), None] }]);
+
+		let mut output = Vec::new();
+		write(&mut output, "int a;\nint b;\n", &annotations).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().collect::<Vec<_>>(), vec![
+			"                                                  │ 1 │ int a;",
+			"global @5: Occupancy 75.0 %, Bandwidth 750.0 MB/s │ 2 │ int b;",
+			"loop (callsite): Occupancy 50.0 %                 │   │",
+			"local: Stall 1.0 %                                │ 4 │ (beyond the end of the file)",
+		]);
+	}
+}
@@ -0,0 +1,406 @@
+use crate::metrics::*;
+use crate::report::*;
+use crate::source_pattern::SourcePattern;
+
+/// Thresholds a profile has to satisfy, read from a TOML file with one `[[threshold]]` table each
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Thresholds
+{
+	#[serde(rename = "threshold", default)]
+	pub thresholds: Vec<Threshold>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "section", rename_all = "snake_case")]
+pub enum Threshold
+{
+	Global(ModuleInstanceThreshold),
+	Local(ModuleInstanceThreshold),
+	Channel(ModuleInstanceThreshold),
+	Loop(ModuleInstanceThreshold),
+	ExternalMemory(ExternalMemoryThreshold),
+}
+
+/// Applies to every module instance group of the section that matches the optional selectors
+#[serde_with::serde_as]
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ModuleInstanceThreshold
+{
+	#[serde_as(as = "serde_with::DisplayFromStr")]
+	pub metric: Metric,
+	#[serde(default)]
+	#[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+	pub source: Option<SourcePattern>,
+	#[serde(default)]
+	#[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+	pub kernel: Option<KernelSelector>,
+	#[serde(flatten)]
+	pub bounds: Bounds,
+}
+
+/// Kernel names as selected by `--kernels`, see `kernel_pattern`, displayed as written
+#[derive(Debug, Clone)]
+pub struct KernelSelector
+{
+	pub pattern: String,
+	pub regex: regex::Regex,
+}
+
+impl std::str::FromStr for KernelSelector
+{
+	type Err = regex::Error;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		Ok(KernelSelector
+		{
+			pattern: string.to_owned(),
+			regex: kernel_pattern(string)?,
+		})
+	}
+}
+
+impl std::fmt::Display for KernelSelector
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}", self.pattern)
+	}
+}
+
+/// Applies to every external memory port that matches the optional selectors
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ExternalMemoryThreshold
+{
+	pub metric: PortMetric,
+	#[serde(default)]
+	pub memory: Option<String>,
+	#[serde(default)]
+	pub port: Option<String>,
+	#[serde(flatten)]
+	pub bounds: Bounds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortMetric
+{
+	Bandwidth,
+	WriteBurst,
+	ReadBurst,
+}
+
+impl PortMetric
+{
+	pub fn name(&self) -> &'static str
+	{
+		match self
+		{
+			PortMetric::Bandwidth => "bandwidth",
+			PortMetric::WriteBurst => "write_burst",
+			PortMetric::ReadBurst => "read_burst",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+pub struct Bounds
+{
+	#[serde(default)]
+	pub min: Option<f32>,
+	#[serde(default)]
+	pub max: Option<f32>,
+}
+
+impl Bounds
+{
+	/// Describes the violated bound, NaN values violate every bound
+	fn violation(&self, value: f32, unit: &str) -> Option<String>
+	{
+		let below = self.min
+			.filter(|&min| value.is_nan() || value < min)
+			.map(|min| format!("expected >= {:.2}{}", min, unit));
+
+		let above = self.max
+			.filter(|&max| value.is_nan() || value > max)
+			.map(|max| format!("expected <= {:.2}{}", max, unit));
+
+		below.or(above)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation
+{
+	pub subject: String,
+	pub reason: String,
+}
+
+impl std::fmt::Display for Violation
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}: {}", self.subject, self.reason)
+	}
+}
+
+impl Thresholds
+{
+	pub fn load(path: &std::path::Path) -> anyhow::Result<Self>
+	{
+		std::fs::read_to_string(path)?.parse()
+	}
+
+	/// The report needs to be expanded if any threshold selects a kernel
+	pub fn evaluate(&self, report: &Report) -> Vec<Violation>
+	{
+		let mut violations = Vec::new();
+
+		for threshold in self.thresholds.iter()
+		{
+			match threshold
+			{
+				Threshold::Global(threshold) => evaluate_module_instances("global memory",
+					&report.global_memory, threshold, &mut violations),
+				Threshold::Local(threshold) => evaluate_module_instances("local memory",
+					&report.local_memory, threshold, &mut violations),
+				Threshold::Channel(threshold) => evaluate_module_instances("channel",
+					&report.channel, threshold, &mut violations),
+				Threshold::Loop(threshold) => evaluate_module_instances("loop",
+					&report.loops, threshold, &mut violations),
+				Threshold::ExternalMemory(threshold) =>
+					evaluate_external_memory(&report.external_memory, threshold, &mut violations),
+			}
+		}
+
+		violations
+	}
+}
+
+impl std::str::FromStr for Thresholds
+{
+	type Err = anyhow::Error;
+
+	/// Rejects thresholds which would always be satisfied or could never be evaluated
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		let thresholds: Thresholds = toml::from_str(string)?;
+
+		for (id, threshold) in thresholds.thresholds.iter().enumerate()
+		{
+			let (section, metric, bounds, available) = match threshold
+			{
+				Threshold::Global(threshold) => ("global", threshold.metric.name(), &threshold.bounds,
+					GlobalMetrics::METRICS.contains(&threshold.metric)),
+				Threshold::Local(threshold) => ("local", threshold.metric.name(), &threshold.bounds,
+					LocalMetrics::METRICS.contains(&threshold.metric)),
+				Threshold::Channel(threshold) => ("channel", threshold.metric.name(), &threshold.bounds,
+					ChannelMetrics::METRICS.contains(&threshold.metric)),
+				Threshold::Loop(threshold) => ("loop", threshold.metric.name(), &threshold.bounds,
+					LoopMetrics::METRICS.contains(&threshold.metric)),
+				Threshold::ExternalMemory(threshold) => ("external_memory", threshold.metric.name(),
+					&threshold.bounds, true),
+			};
+
+			let name = format!("threshold {} ({} {})", id + 1, section, metric);
+
+			if bounds.min.is_none() && bounds.max.is_none()
+			{
+				anyhow::bail!("{} has neither min nor max", name);
+			}
+			if !available
+			{
+				anyhow::bail!("{}: the {} section has no {}", name, section, metric);
+			}
+		}
+
+		Ok(thresholds)
+	}
+}
+
+fn evaluate_module_instances<Metrics>(section: &str, groups: &[ModuleInstanceGroup<Metrics>],
+	threshold: &ModuleInstanceThreshold, violations: &mut Vec<Violation>)
+where
+	Metrics: MetricValues,
+{
+	let candidates = groups.iter()
+		.filter(|group|
+			threshold.source.as_ref()
+				.map(|source| source.matches(&group.source_files))
+				.unwrap_or(true))
+		.flat_map(|group|
+		{
+			let location = group.source_files.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>().join(", ");
+
+			match &threshold.kernel
+			{
+				Some(kernel_selector) => group.kernels.iter().flatten()
+					.filter(|kernel| kernel_selector.regex.is_match(&kernel.name))
+					.map(|kernel| (format!("{} in kernel {}", location, kernel.name), &kernel.metrics))
+					.collect::<Vec<_>>(),
+				None => vec![(location, &group.metrics)],
+			}
+		})
+		.collect::<Vec<_>>();
+
+	if candidates.is_empty()
+	{
+		violations.push(Violation
+		{
+			subject: format!("{} of {}{}{}", threshold.metric, section,
+				threshold.source.as_ref().map(|source| format!(" at {}", source)).unwrap_or_default(),
+				threshold.kernel.as_ref().map(|kernel| format!(" in kernel {}", kernel))
+					.unwrap_or_default()),
+			reason: "no module instance matched".to_owned(),
+		});
+	}
+
+	for (location, metrics) in candidates.into_iter()
+	{
+		let subject = format!("{} of {} at {}", threshold.metric, section, location);
+		let unit = threshold.metric.unit();
+
+		let reason = match metrics.value(threshold.metric)
+		{
+			Some(value) => threshold.bounds.violation(value, unit)
+				.map(|expectation| format!("{:.2}{}, {}", value, unit, expectation)),
+			None => Some("metric not available".to_owned()),
+		};
+
+		if let Some(reason) = reason
+		{
+			violations.push(Violation { subject, reason });
+		}
+	}
+}
+
+fn evaluate_external_memory(external_memories: &[ExternalMemorySummary],
+	threshold: &ExternalMemoryThreshold, violations: &mut Vec<Violation>)
+{
+	let (metric_name, unit) = match threshold.metric
+	{
+		PortMetric::Bandwidth => ("bandwidth", " MB/s"),
+		PortMetric::WriteBurst => ("write burst", ""),
+		PortMetric::ReadBurst => ("read burst", ""),
+	};
+
+	let ports = external_memories.iter()
+		.filter(|external_memory|
+			threshold.memory.as_ref().map(|memory| memory == &external_memory.name).unwrap_or(true))
+		.flat_map(|external_memory|
+			external_memory.ports.iter()
+				.filter(|port|
+					threshold.port.as_ref().map(|name| name == &port.port).unwrap_or(true))
+				.map(move |port| (&external_memory.name, port)))
+		.collect::<Vec<_>>();
+
+	if ports.is_empty()
+	{
+		violations.push(Violation
+		{
+			subject: format!("{} of external memory {} port {}", metric_name,
+				threshold.memory.as_deref().unwrap_or("*"), threshold.port.as_deref().unwrap_or("*")),
+			reason: "no port matched".to_owned(),
+		});
+	}
+
+	for (name, port) in ports.into_iter()
+	{
+		let value = match threshold.metric
+		{
+			PortMetric::Bandwidth => port.bandwidth,
+			PortMetric::WriteBurst => port.write_burst,
+			PortMetric::ReadBurst => port.read_burst,
+		};
+
+		if let Some(expectation) = threshold.bounds.violation(value, unit)
+		{
+			violations.push(Violation
+			{
+				subject: format!("{} of external memory {} port {}", metric_name, name, port.port),
+				reason: format!("{:.2}{}, {}", value, unit, expectation),
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn violations(thresholds: &str) -> Vec<String>
+	{
+		let mut report = Report::new(&fixtures::load(fixtures::example()),
+			&Settings { expand: true, ..Default::default() });
+		report.external_memory = vec![ExternalMemorySummary
+		{
+			name: "DDR".to_owned(),
+			ports: vec![PortSummary { port: "X".to_owned(), bandwidth: 4000., write_burst: 2., read_burst: 8. }],
+		}];
+
+		thresholds.parse::<Thresholds>().unwrap()
+			.evaluate(&report).iter()
+			.map(ToString::to_string)
+			.collect()
+	}
+
+	#[test]
+	fn parse_rejects_thresholds_that_cannot_fail()
+	{
+		let error = |thresholds: &str| thresholds.parse::<Thresholds>().unwrap_err().to_string();
+
+		assert_eq!("[[threshold]]\nsection = \"loop\"\nmetric = \"occupancy\"\nmin = 90\n\
+				[[threshold]]\nsection = \"global\"\nmetric = \"stall\"\nkernel = \".*K\"\nmax = 20\n"
+			.parse::<Thresholds>().unwrap().thresholds.len(), 2);
+		assert_eq!(error("[[threshold]]\nsection = \"loop\"\nmetric = \"occupancy\"\nmin = 90\n\
+				[[threshold]]\nsection = \"local\"\nmetric = \"stall\"\n"),
+			"threshold 2 (local stall) has neither min nor max");
+		assert_eq!(error("[[threshold]]\nsection = \"loop\"\nmetric = \"bandwidth\"\nmin = 1\n"),
+			"threshold 1 (loop bandwidth): the loop section has no bandwidth");
+		assert!(error("[[threshold]]\nsection = \"loop\"\nmetric = \"occupancy\"\nkernel = \"(\"\nmin = 1\n")
+			.contains("regex"));
+		assert!(error("[[threshold]]\nsection = \"loop\"\nmetric = \"latency\"\nmin = 1\n")
+			.contains("unknown metric"));
+	}
+
+	#[test]
+	fn evaluate_checks_the_bounds()
+	{
+		let global = |bounds: &str|
+			violations(&format!("[[threshold]]\nsection = \"global\"\nmetric = \"occupancy\"\n{}\n", bounds));
+
+		assert!(global("min = 70").is_empty());
+		assert!(global("min = 70\nmax = 80").is_empty());
+		assert_eq!(global("min = 80"),
+			vec!["occupancy of global memory at /src/kernel.cpp:10: 75.00 %, expected >= 80.00 %"]);
+		assert_eq!(global("max = 70"),
+			vec!["occupancy of global memory at /src/kernel.cpp:10: 75.00 %, expected <= 70.00 %"]);
+
+		assert_eq!(Bounds { min: Some(0.), max: None }.violation(f32::NAN, ""), Some("expected >= 0.00".to_owned()));
+	}
+
+	#[test]
+	fn evaluate_selects_kernels_by_pattern_and_ports_by_name()
+	{
+		let loops = |selector: &str|
+			violations(&format!("[[threshold]]\nsection = \"loop\"\nmetric = \"occupancy\"\nmin = 60\n{}\n",
+				selector));
+
+		assert_eq!(loops("kernel = \"[A-Z]\""),
+			vec!["occupancy of loop at /src/kernel.cpp:20 in kernel K: 50.00 %, expected >= 60.00 %"]);
+		assert_eq!(loops("kernel = \"K.+\""), vec!["occupancy of loop in kernel K.+: no module instance matched"]);
+		assert_eq!(loops("source = \"kernel.cpp:1-19\""),
+			vec!["occupancy of loop at kernel.cpp:1-19: no module instance matched"]);
+
+		let port = |selector: &str|
+			violations(&format!("[[threshold]]\nsection = \"external_memory\"\nmetric = \"read_burst\"\nmax = 4\n{}\n",
+				selector));
+
+		assert_eq!(port("port = \"X\""), vec!["read burst of external memory DDR port X: 8.00, expected <= 4.00"]);
+		assert_eq!(port("memory = \"HBM\""), vec!["read burst of external memory HBM port *: no port matched"]);
+	}
+}
@@ -1,24 +1,228 @@
 #[derive(Debug, Clone, serde::Deserialize)]
+#[serde(from = "Fields<Nodes>")]
 pub struct Profile
 {
 	pub json_type: String,
 	pub versions: Versions,
 	pub kernels: Nodes,
 	pub boards: Nodes,
-	#[serde(rename = "memtransfers")]
 	pub memory_transfers: Nodes,
 	pub channels: Nodes,
-	#[serde(rename = "run_info")]
 	pub run_information: Nodes,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
+/// Profile with the samples of every kernel invocation folded into their sums right after the
+/// invocation is read, see [`crate::metrics::fold_samples`]
+#[derive(Debug, Clone)]
+pub struct FoldedProfile(pub Profile);
+
+impl<'de> serde::Deserialize<'de> for FoldedProfile
+{
+	fn deserialize<Deserializer>(deserializer: Deserializer) -> Result<Self, Deserializer::Error>
+	where
+		Deserializer: serde::Deserializer<'de>,
+	{
+		Fields::<FoldedNodes>::deserialize(deserializer).map(|fields| FoldedProfile(fields.into()))
+	}
+}
+
+/// Fields of a profile, whose kernels are deserialized as `Kernels`
+#[derive(serde::Deserialize)]
+struct Fields<Kernels>
+{
+	json_type: String,
+	versions: Versions,
+	kernels: Kernels,
+	boards: Nodes,
+	#[serde(rename = "memtransfers")]
+	memory_transfers: Nodes,
+	channels: Nodes,
+	#[serde(rename = "run_info")]
+	run_information: Nodes,
+}
+
+impl<Kernels: Into<Nodes>> From<Fields<Kernels>> for Profile
+{
+	fn from(fields: Fields<Kernels>) -> Self
+	{
+		Profile
+		{
+			json_type: fields.json_type,
+			versions: fields.versions,
+			kernels: fields.kernels.into(),
+			boards: fields.boards,
+			memory_transfers: fields.memory_transfers,
+			channels: fields.channels,
+			run_information: fields.run_information,
+		}
+	}
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Versions
 {
 	pub profiler_json_version: String,
 	pub aocx_version: String,
 }
 
+/// Releases of the profiler whose profiles have been tested
+///
+/// Profiles of every release are read with the same leniency, as there is no mapping of fields
+/// renamed or removed by other releases yet: missing required fields fail the loading, missing
+/// optional ones are reported as [`Warning::MissingField`] instead of being assumed.
+pub const TESTED_RELEASES: &[&str] = &["2021.2"];
+
+impl Versions
+{
+	/// Year and update of the profiler, e.g. `2021.2` of `2021.2.0.268.1`
+	pub fn release(&self) -> &str
+	{
+		let end = self.profiler_json_version.match_indices('.')
+			.nth(1)
+			.map(|(index, _)| index)
+			.unwrap_or(self.profiler_json_version.len());
+
+		&self.profiler_json_version[..end]
+	}
+}
+
+/// Deviation from the tested profiles, once per occurrence
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Warning
+{
+	UntestedRelease(String),
+	/// Optional field absent in an element, e.g. removed by another release, which is left unknown
+	MissingField
+	{
+		element: &'static str,
+		field: &'static str,
+	},
+	/// Element of an unknown type with its fields
+	Skipped
+	{
+		element: &'static str,
+		tag: String,
+		fields: Vec<String>,
+	},
+}
+
+impl std::fmt::Display for Warning
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		match self
+		{
+			Warning::UntestedRelease(version) => write!(formatter,
+				"profiler_json_version {} has not been tested (tested: {})",
+				version, TESTED_RELEASES.join(", ")),
+			Warning::MissingField { element, field } => write!(formatter,
+				"{} without {}, which is left unknown", element, field),
+			Warning::Skipped { element, tag, fields } => write!(formatter,
+				"skipped {} of the unknown type {} (fields: {})", element, tag, fields.join(", ")),
+		}
+	}
+}
+
+impl Profile
+{
+	/// Deviations from the tested profiles, e.g. missing optional fields or elements of unknown types
+	/// that were skipped
+	pub fn warnings(&self) -> Vec<Warning>
+	{
+		let mut warnings = Vec::new();
+
+		if !TESTED_RELEASES.contains(&self.versions.release())
+		{
+			warnings.push(Warning::UntestedRelease(self.versions.profiler_json_version.clone()));
+		}
+
+		let nodes = [&self.kernels, &self.boards, &self.memory_transfers, &self.channels, &self.run_information]
+			.iter()
+			.flat_map(|nodes| nodes.nodes.iter())
+			.collect::<Vec<_>>();
+
+		let children = nodes.iter()
+			.flat_map(|node|
+				match node
+				{
+					Node::Kernel(kernel) => kernel.children.iter(),
+					Node::Board(board) => board.children.iter(),
+					_ => [].iter(),
+				})
+			.collect::<Vec<_>>();
+
+		let unknown_nodes = nodes.iter()
+			.filter_map(|node|
+				match node
+				{
+					Node::Unknown(unknown) => Some(("node", unknown)),
+					_ => None
+				});
+
+		let unknown_children = children.iter()
+			.filter_map(|child|
+				match child
+				{
+					Child::Unknown(unknown) => Some(("child", unknown)),
+					_ => None
+				});
+
+		let unknown_module_instances = children.iter()
+			.filter_map(|child|
+				match child
+				{
+					Child::ModuleInstance(ModuleInstance
+					{
+						module_instance_details: ModuleInstanceDetails::Unknown(unknown), ..
+					}) => Some(("module instance", unknown)),
+					_ => None
+				});
+
+		let kernels = nodes.iter()
+			.filter_map(|node|
+				match node
+				{
+					Node::Kernel(kernel) => Some(("kernel", "shared_counter_run_type",
+						kernel.shared_counter_run_type.is_some())),
+					_ => None
+				});
+
+		let module_instances = children.iter()
+			.filter_map(|child|
+				match child
+				{
+					Child::ModuleInstance(module_instance) => match &module_instance.module_instance_details
+					{
+						ModuleInstanceDetails::Global(Global { coalesced_memory, .. })
+							| ModuleInstanceDetails::Local(Local { coalesced_memory, .. })
+							| ModuleInstanceDetails::Channel(Channel { coalesced_memory, .. }) =>
+							Some(("module instance", "coalesced_memory", coalesced_memory.is_some())),
+						_ => None
+					},
+					Child::ExternalMemory(external_memory) =>
+						Some(("external memory", "interface", external_memory.interface.is_some())),
+					_ => None
+				});
+
+		warnings.extend(kernels.chain(module_instances)
+			.filter(|&(_, _, present)| !present)
+			.map(|(element, field, _)| Warning::MissingField { element, field }));
+
+		warnings.extend(unknown_nodes.chain(unknown_children).chain(unknown_module_instances)
+			.map(|(element, unknown)|
+				Warning::Skipped
+				{
+					element,
+					tag: unknown.tag.clone(),
+					fields: unknown.raw.as_object().into_iter()
+						.flat_map(|object| object.keys().cloned())
+						.collect(),
+				}));
+
+		warnings
+	}
+}
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Nodes
 {
@@ -26,78 +230,340 @@ pub struct Nodes
 	pub nodes: Vec<Node>,
 }
 
+/// Nodes whose kernel invocations have their samples folded once they are read
+#[derive(serde::Deserialize)]
+struct FoldedNodes
+{
+	#[serde(deserialize_with = "folded_nodes")]
+	nodes: Vec<Node>,
+}
+
+impl From<FoldedNodes> for Nodes
+{
+	fn from(nodes: FoldedNodes) -> Self
+	{
+		Nodes { nodes: nodes.nodes }
+	}
+}
+
+/// Nodes as [`empty_string_as_empty_vec`], the samples of each kernel invocation folded once it is
+/// read
+fn folded_nodes<'de, Deserializer>(deserializer: Deserializer) -> Result<Vec<Node>, Deserializer::Error>
+where
+	Deserializer: serde::Deserializer<'de>,
+{
+	deserializer.deserialize_any(ArrayVisitor(|node|
+		match node
+		{
+			Node::Kernel(mut kernel) =>
+			{
+				crate::metrics::fold_samples(&mut kernel);
+
+				Node::Kernel(kernel)
+			}
+			node => node,
+		}))
+}
+
+/// Accepts an empty string in place of an empty array, streaming the elements of an array instead
+/// of buffering them as an untagged enum would
 fn empty_string_as_empty_vec<'de, Deserializer, Value>(deserializer: Deserializer)
 	-> Result<Vec<Value>, Deserializer::Error>
 where
 	Deserializer: serde::Deserializer<'de>,
 	Value: serde::Deserialize<'de>,
 {
-	use serde::Deserialize as _;
-	use serde::de::Error as _;
+	deserializer.deserialize_any(ArrayVisitor(|value| value))
+}
+
+/// Elements of an array or an empty string, each passed through the function once it is read
+struct ArrayVisitor<Value>(fn(Value) -> Value);
+
+impl<'de, Value: serde::Deserialize<'de>> serde::de::Visitor<'de> for ArrayVisitor<Value>
+{
+	type Value = Vec<Value>;
 
-	#[derive(Debug, Clone, serde::Deserialize)]
-	#[serde(untagged)]
-	enum StringOrVec<Value>
+	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
 	{
-		String(String),
-		Vec(Vec<Value>),
+		write!(formatter, "an array or an empty string")
 	}
 
-	match StringOrVec::deserialize(deserializer)?
+	fn visit_str<Error: serde::de::Error>(self, string: &str) -> Result<Self::Value, Error>
 	{
-		StringOrVec::String(string) =>
+		match string
 		{
-			match string.as_ref()
+			"" => Ok(vec![]),
+			_ => Err(Error::custom("non-empty string instead of array not supported")),
+		}
+	}
+
+	fn visit_seq<Sequence: serde::de::SeqAccess<'de>>(self, mut sequence: Sequence)
+		-> Result<Self::Value, Sequence::Error>
+	{
+		let mut values = Vec::with_capacity(sequence.size_hint().unwrap_or_default());
+
+		while let Some(value) = sequence.next_element()?
+		{
+			values.push((self.0)(value));
+		}
+
+		Ok(values)
+	}
+}
+
+/// Number or boolean as string, as written by the profiler, but also as plain JSON value
+pub struct NumberOrString;
+
+impl<'de, Value> serde_with::DeserializeAs<'de, Value> for NumberOrString
+where
+	Value: std::str::FromStr,
+	Value::Err: std::fmt::Display,
+{
+	fn deserialize_as<Deserializer>(deserializer: Deserializer) -> Result<Value, Deserializer::Error>
+	where
+		Deserializer: serde::Deserializer<'de>,
+	{
+		struct Visitor<Value>(std::marker::PhantomData<Value>);
+
+		impl<'de, Value> serde::de::Visitor<'de> for Visitor<Value>
+		where
+			Value: std::str::FromStr,
+			Value::Err: std::fmt::Display,
+		{
+			type Value = Value;
+
+			fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
 			{
-				"" => Ok(vec![]),
-				_ => Err(Deserializer::Error::custom(
-					"non-empty string instead of array not supported"))
+				write!(formatter, "a number or boolean, optionally as string")
+			}
+
+			fn visit_str<Error: serde::de::Error>(self, string: &str) -> Result<Self::Value, Error>
+			{
+				string.parse().map_err(Error::custom)
+			}
+
+			fn visit_bool<Error: serde::de::Error>(self, value: bool) -> Result<Self::Value, Error>
+			{
+				self.visit_str(&value.to_string())
+			}
+
+			fn visit_i64<Error: serde::de::Error>(self, value: i64) -> Result<Self::Value, Error>
+			{
+				self.visit_str(&value.to_string())
+			}
+
+			fn visit_u64<Error: serde::de::Error>(self, value: u64) -> Result<Self::Value, Error>
+			{
+				self.visit_str(&value.to_string())
+			}
+
+			fn visit_f64<Error: serde::de::Error>(self, value: f64) -> Result<Self::Value, Error>
+			{
+				self.visit_str(&value.to_string())
 			}
 		}
-		StringOrVec::Vec(vec) => Ok(vec),
+
+		deserializer.deserialize_any(Visitor(std::marker::PhantomData))
 	}
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")]
+/// Internally tagged enum, whose variants are deserialized straight from the map if the tag is its
+/// first key, whereas serde buffers every variant in full, including all of its samples; otherwise
+/// the object is buffered as JSON text, see `TaggedVisitor`
+trait Tagged: Sized
+{
+	const TAG: &'static str;
+
+	/// Captures the raw JSON of an unknown tag instead of failing
+	fn deserialize_variant<'de, Deserializer>(tag: &str, deserializer: Deserializer)
+		-> Result<Self, Deserializer::Error>
+	where
+		Deserializer: serde::Deserializer<'de>;
+}
+
+/// Element of a type unknown to the data model, e.g. introduced by a newer profiler, which is
+/// skipped by the aggregation
+#[derive(Debug, Clone)]
+pub struct UnknownElement
+{
+	pub tag: String,
+	/// Whole element including the tag
+	pub raw: serde_json::Value,
+}
+
+impl UnknownElement
+{
+	fn deserialize<'de, Deserializer>(tag_key: &str, tag: &str, deserializer: Deserializer)
+		-> Result<Self, Deserializer::Error>
+	where
+		Deserializer: serde::Deserializer<'de>,
+	{
+		use serde::Deserialize as _;
+
+		let mut raw = serde_json::Map::new();
+		raw.insert(tag_key.to_owned(), serde_json::Value::String(tag.to_owned()));
+		raw.extend(serde_json::Map::deserialize(deserializer)?);
+
+		Ok(UnknownElement
+		{
+			tag: tag.to_owned(),
+			raw: serde_json::Value::Object(raw),
+		})
+	}
+}
+
+/// Reads the variant straight from the map if the tag comes first, otherwise the object is buffered as
+/// JSON text, with the tag taken out, until the tag is known
+struct TaggedVisitor<Enum>(std::marker::PhantomData<Enum>);
+
+impl<'de, Enum: Tagged> serde::de::Visitor<'de> for TaggedVisitor<Enum>
+{
+	type Value = Enum;
+
+	fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "an object with the tag {}", Enum::TAG)
+	}
+
+	fn visit_map<Map: serde::de::MapAccess<'de>>(self, mut map: Map) -> Result<Self::Value, Map::Error>
+	{
+		use serde::de::Error as _;
+
+		let first_key = map.next_key::<String>()?
+			.ok_or_else(|| Map::Error::missing_field(Enum::TAG))?;
+
+		if first_key == Enum::TAG
+		{
+			let tag = map.next_value::<String>()?;
+
+			return Enum::deserialize_variant(&tag, serde::de::value::MapAccessDeserializer::new(map));
+		}
+
+		// Otherwise the entries are collected as JSON text until the object is read, which holds the
+		// samples in a fraction of the memory of parsed values, and the variant is deserialized from it
+		let mut object = String::from("{");
+		let mut tag = None;
+		let mut key = Some(first_key);
+
+		while let Some(current_key) = key
+		{
+			if current_key == Enum::TAG
+			{
+				tag = Some(map.next_value::<serde_json::Value>()?);
+			}
+			else
+			{
+				let value = map.next_value::<Box<serde_json::value::RawValue>>()?;
+
+				if object.len() > 1
+				{
+					object.push(',');
+				}
+				object.push_str(&serde_json::to_string(&current_key).map_err(Map::Error::custom)?);
+				object.push(':');
+				object.push_str(value.get());
+			}
+
+			key = map.next_key()?;
+		}
+
+		object.push('}');
+
+		let tag = match tag
+		{
+			Some(serde_json::Value::String(tag)) => tag,
+			Some(_) => return Err(Map::Error::custom(format!("{} is not a string", Enum::TAG))),
+			None => return Err(Map::Error::missing_field(Enum::TAG)),
+		};
+
+		Enum::deserialize_variant(&tag, &mut serde_json::Deserializer::from_str(&object))
+			.map_err(Map::Error::custom)
+	}
+}
+
+macro_rules! impl_tagged
+{
+	($enum_name:ident, $tag:literal, { $($variant_tag:literal => $variant:ident($type:ident)),* $(,)? }) =>
+	{
+		impl Tagged for $enum_name
+		{
+			const TAG: &'static str = $tag;
+
+			fn deserialize_variant<'de, Deserializer>(tag: &str, deserializer: Deserializer)
+				-> Result<Self, Deserializer::Error>
+			where
+				Deserializer: serde::Deserializer<'de>,
+			{
+				use serde::Deserialize as _;
+
+				match tag
+				{
+					$($variant_tag => $type::deserialize(deserializer).map($enum_name::$variant),)*
+					_ => UnknownElement::deserialize(Self::TAG, tag, deserializer).map($enum_name::Unknown),
+				}
+			}
+		}
+
+		impl<'de> serde::Deserialize<'de> for $enum_name
+		{
+			fn deserialize<Deserializer>(deserializer: Deserializer) -> Result<Self, Deserializer::Error>
+			where
+				Deserializer: serde::Deserializer<'de>,
+			{
+				deserializer.deserialize_map(TaggedVisitor(std::marker::PhantomData))
+			}
+		}
+	};
+}
+
+#[derive(Debug, Clone)]
 pub enum Node
 {
 	Kernel(Kernel),
 	Board(Board),
-	#[serde(rename = "memtransfers")]
 	MemoryTransfers(MemoryTransfers),
-	#[serde(rename = "runinfo")]
 	RunInformation(RunInformation),
+	Unknown(UnknownElement),
 }
 
+impl_tagged!(Node, "type",
+{
+	"kernel" => Kernel(Kernel),
+	"board" => Board(Board),
+	"memtransfers" => MemoryTransfers(MemoryTransfers),
+	"runinfo" => RunInformation(RunInformation),
+});
+
 #[serde_with::serde_as]
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Kernel
 {
 	pub name: String,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub compute_unit: u32,
 	#[serde(rename = "sourcefile")]
 	pub source_file: FileReference,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub device_ids: Vec<u32>,
 	#[serde(default)]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub command_queue_ids: Vec<u32>,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub start_time: u64,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub end_time: u64,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub num_samples: u32,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
-	pub shared_counter_run_type: i32,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	/// Absent in a profile is reported as [`Warning::MissingField`], as are the other optional fields
+	#[serde(default)]
+	#[serde_as(as = "Option<NumberOrString>")]
+	pub shared_counter_run_type: Option<i32>,
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub sample_timestamps: Vec<u64>,
 	#[serde(default)]
-	#[serde_as(as = "Option<Vec<serde_with::DisplayFromStr>>")]
+	#[serde_as(as = "Option<Vec<NumberOrString>>")]
 	pub total_cycles_between_samples: Option<Vec<u64>>,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub is_autorun: bool,
 	#[serde(deserialize_with = "empty_string_as_empty_vec")]
 	pub children: Vec<Child>,
@@ -115,13 +581,13 @@ pub struct Board
 pub struct MemoryTransfers
 {
 	pub type_transfer: String,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub device_id: u32,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub command_queue_id: u32,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub start_time: u64,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub end_time: u64,
 }
 
@@ -129,23 +595,26 @@ pub struct MemoryTransfers
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct RunInformation
 {
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub fmax: f32,
 }
 
-#[serde_with::serde_as]
-#[derive(Debug, Clone, serde::Deserialize)]
-#[serde(tag = "type")]
+#[derive(Debug, Clone)]
 pub enum Child
 {
-	#[serde(rename = "moduleinst")]
 	ModuleInstance(ModuleInstance),
-	#[serde(rename = "globalmem")]
 	GlobalMemory(GlobalMemory),
-	#[serde(rename = "extmem")]
 	ExternalMemory(ExternalMemory),
+	Unknown(UnknownElement),
 }
 
+impl_tagged!(Child, "type",
+{
+	"moduleinst" => ModuleInstance(ModuleInstance),
+	"globalmem" => GlobalMemory(GlobalMemory),
+	"extmem" => ExternalMemory(ExternalMemory),
+});
+
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct ModuleInstance
 {
@@ -162,10 +631,10 @@ pub struct GlobalMemory
 {
 	pub global_memory_name: String,
 	#[serde(rename = "max_theoretical_globalmem_bw")]
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub maximum_theoretical_global_memory_bandwidth: f32,
 	#[serde(rename = "max_burst_count")]
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(as = "NumberOrString")]
 	pub maximum_burst_count: f32,
 }
 
@@ -174,75 +643,100 @@ pub struct GlobalMemory
 pub struct ExternalMemory
 {
 	pub name: String,
-	pub interface: String,
+	#[serde(default)]
+	pub interface: Option<String>,
 	pub port: String,
 	#[serde(rename = "global_used_bw")]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub global_used_bandwidth: Vec<f32>,
 	#[serde(rename = "avg_write_burst")]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub average_write_burst: Vec<f32>,
 	#[serde(rename = "avg_read_burst")]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub average_read_burst: Vec<f32>,
 }
 
 #[serde_with::serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
 pub struct FileReference
 {
-	#[serde(rename = "filename")]
+	#[serde(rename(deserialize = "filename"))]
 	pub file_name: std::path::PathBuf,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
+	#[serde_as(deserialize_as = "NumberOrString")]
 	pub line: u32,
-	#[serde(rename = "column_num", default)]
-	#[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+	#[serde(rename(deserialize = "column_num"), default)]
+	#[serde_as(deserialize_as = "Option<NumberOrString>")]
 	pub column_number: Option<u32>,
 	#[serde(default)]
 	pub callsite: Vec<FileReference>,
 }
 
-#[derive(Debug, Clone, serde::Deserialize)]
-#[serde(tag = "mem_type")]
+/// Compact form, e.g. `kernel.cpp:42:5`, without the callsite
+impl std::fmt::Display for FileReference
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}:{}", self.file_name.display(), self.line)?;
+
+		if let Some(column_number) = self.column_number
+		{
+			write!(formatter, ":{}", column_number)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone)]
 pub enum ModuleInstanceDetails
 {
-	#[serde(rename = "__global")]
 	Global(Global),
-	#[serde(rename = "__local")]
 	Local(Local),
-	#[serde(rename = "__channel")]
 	Channel(Channel),
-	#[serde(rename = "__loop")]
 	Loop(Loop),
+	Unknown(UnknownElement),
 }
 
+impl_tagged!(ModuleInstanceDetails, "mem_type",
+{
+	"__global" => Global(Global),
+	"__local" => Local(Local),
+	"__channel" => Channel(Channel),
+	"__loop" => Loop(Loop),
+});
+
 #[serde_with::serde_as]
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Global
 {
 	pub operation_type: OperationType,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub occupancy_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub stall_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub idle_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub activity_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub bandwidth_samples: Vec<f32>,
 	#[serde(rename = "bandwidth_eff_samples")]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub bandwidth_effective_samples: Vec<f32>,
 	#[serde(default)]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub cache_hit_samples: Vec<u64>,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
-	pub coalesced_memory: bool,
+	#[serde(default)]
+	#[serde_as(as = "Option<NumberOrString>")]
+	pub coalesced_memory: Option<bool>,
 	#[serde(rename = "global_mem_name")]
 	pub global_memory_name: String,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub average_burst_size: Vec<f32>,
+	/// Replaces the samples if these were folded while loading
+	#[serde(skip)]
+	pub sample_sums: Option<Box<SampleSums>>,
 }
 
 #[serde_with::serde_as]
@@ -250,16 +744,20 @@ pub struct Global
 pub struct Local
 {
 	pub operation_type: OperationType,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub occupancy_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub stall_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub idle_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub activity_samples: Vec<u64>,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
-	pub coalesced_memory: bool,
+	#[serde(default)]
+	#[serde_as(as = "Option<NumberOrString>")]
+	pub coalesced_memory: Option<bool>,
+	/// Replaces the samples if these were folded while loading
+	#[serde(skip)]
+	pub sample_sums: Option<Box<SampleSums>>,
 }
 
 #[serde_with::serde_as]
@@ -267,31 +765,57 @@ pub struct Local
 pub struct Channel
 {
 	pub operation_type: OperationType,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub occupancy_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub stall_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub idle_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub average_channel_depth_samples: Vec<f32>,
 	#[serde(rename = "max_channel_depth_samples")]
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub maximum_channel_depth_samples: Vec<u32>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub activity_samples: Vec<u64>,
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub bandwidth_samples: Vec<f32>,
-	#[serde_as(as = "serde_with::DisplayFromStr")]
-	pub coalesced_memory: bool,
+	#[serde(default)]
+	#[serde_as(as = "Option<NumberOrString>")]
+	pub coalesced_memory: Option<bool>,
+	/// Replaces the samples if these were folded while loading
+	#[serde(skip)]
+	pub sample_sums: Option<Box<SampleSums>>,
 }
 
 #[serde_with::serde_as]
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct Loop
 {
-	#[serde_as(as = "Vec<serde_with::DisplayFromStr>")]
+	#[serde_as(as = "Vec<NumberOrString>")]
 	pub occupancy_samples: Vec<u64>,
+	/// Replaces the samples if these were folded while loading
+	#[serde(skip)]
+	pub sample_sums: Option<Box<SampleSums>>,
+}
+
+/// Sums over the samples of a module instance within its kernel invocation, which are all the metrics
+/// are computed from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleSums
+{
+	/// Occupancy and cycles
+	pub occupancy: (u64, u64),
+	/// Stall, idle, activity and cycles
+	pub stall: (u64, u64, u64, u64),
+	/// Bandwidth multiplied by the length of the intervals
+	pub bandwidth: f32,
+	/// Number of samples, efficiency and average burst size
+	pub effectiveness: (u32, f32, f32),
+	/// Whether there are cache hit samples, cache hits and occupancy
+	pub cache_hit: (bool, u64, u64),
+	/// Number of samples, average and maximum channel depth
+	pub channel_depth: (u32, f32, u32),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
@@ -301,3 +825,171 @@ pub enum OperationType
 	Read,
 	Write,
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn run_information(fmax: serde_json::Value) -> serde_json::Value
+	{
+		serde_json::json!({"type": "runinfo", "fmax": fmax})
+	}
+
+	#[test]
+	fn tagged_reads_the_tag_at_any_position()
+	{
+		let first: Node = serde_json::from_str(r#"{"type": "runinfo", "fmax": "300"}"#).unwrap();
+		let last: Node = serde_json::from_str(r#"{"fmax": "300", "type": "runinfo"}"#).unwrap();
+
+		for node in [first, last].iter()
+		{
+			match node
+			{
+				Node::RunInformation(run_information) => assert_eq!(run_information.fmax, 300.),
+				node => panic!("unexpected node {:?}", node),
+			}
+		}
+
+		assert!(serde_json::from_str::<Node>(r#"{"fmax": "300"}"#).is_err());
+		assert!(serde_json::from_str::<Node>(r#"{"fmax": "300", "type": 1}"#).is_err());
+	}
+
+	#[test]
+	fn tagged_buffers_nested_elements_with_the_tag_after_their_samples()
+	{
+		// Ordered as written, see the `preserve_order` feature
+		let loop_details = |tag_first: bool|
+			match tag_first
+			{
+				true => serde_json::json!({"mem_type": "__loop", "occupancy_samples": ["1000", 500]}),
+				false => serde_json::json!({"occupancy_samples": ["1000", 500], "mem_type": "__loop"}),
+			};
+
+		let kernel = |tag_first: bool|
+		{
+			let mut kernel = crate::fixtures::kernel("K", 0, &[1000, 2000], vec![
+				crate::fixtures::module_instance("loop", "kernel.cpp", 20, loop_details(tag_first)),
+			]);
+
+			if !tag_first
+			{
+				let object = kernel.as_object_mut().unwrap();
+				let tag = object.shift_remove("type").unwrap();
+				object.insert("type".to_owned(), tag);
+			}
+
+			kernel
+		};
+
+		let expected = format!("{:?}", serde_json::from_value::<Node>(kernel(true)).unwrap());
+		let json = kernel(false).to_string();
+
+		assert!(json.ends_with(r#""type":"kernel"}"#));
+		assert_eq!(format!("{:?}", serde_json::from_str::<Node>(&json).unwrap()), expected);
+		assert_eq!(format!("{:?}", serde_json::from_reader::<_, Node>(json.as_bytes()).unwrap()), expected);
+		assert_eq!(format!("{:?}", serde_json::from_value::<Node>(kernel(false)).unwrap()), expected);
+
+		let folded = serde_json::from_str::<FoldedProfile>(&crate::fixtures::profile(vec![kernel(false)])
+			.to_string())
+			.unwrap();
+
+		match &folded.0.kernels.nodes[..]
+		{
+			[Node::Kernel(kernel)] => match &kernel.children[..]
+			{
+				[Child::ModuleInstance(ModuleInstance
+				{
+					module_instance_details: ModuleInstanceDetails::Loop(sample), ..
+				})] => assert_eq!(sample.sample_sums.as_ref().unwrap().occupancy, (1500, 2000)),
+				children => panic!("unexpected children {:?}", children),
+			},
+			nodes => panic!("unexpected nodes {:?}", nodes),
+		}
+	}
+
+	#[test]
+	fn warnings_report_missing_optional_fields_and_untested_releases()
+	{
+		let mut profile = crate::fixtures::example();
+		assert_eq!(crate::fixtures::load(profile.clone()).warnings(), vec![]);
+
+		profile["versions"]["profiler_json_version"] = "2024.1.0".into();
+		let kernel = &mut profile["kernels"]["nodes"][0];
+		kernel.as_object_mut().unwrap().remove("shared_counter_run_type");
+		kernel["children"][0]["module_inst_details"].as_object_mut().unwrap().remove("coalesced_memory");
+		kernel["children"].as_array_mut().unwrap().push(serde_json::json!({
+			"type": "extmem",
+			"name": "DDR",
+			"port": "0",
+			"global_used_bw": [],
+			"avg_write_burst": [],
+			"avg_read_burst": [],
+		}));
+
+		let warnings = crate::fixtures::load(profile).warnings();
+
+		assert_eq!(warnings.iter().map(|warning| warning.to_string()).collect::<Vec<_>>(), vec![
+			"profiler_json_version 2024.1.0 has not been tested (tested: 2021.2)",
+			"kernel without shared_counter_run_type, which is left unknown",
+			"module instance without coalesced_memory, which is left unknown",
+			"external memory without interface, which is left unknown",
+		]);
+	}
+
+	#[test]
+	fn tagged_keeps_unknown_elements_with_their_tag()
+	{
+		for json in [r#"{"type": "pipe", "name": "p"}"#, r#"{"name": "p", "type": "pipe"}"#].iter()
+		{
+			match serde_json::from_str::<Child>(json).unwrap()
+			{
+				Child::Unknown(unknown) =>
+				{
+					assert_eq!(unknown.tag, "pipe");
+					assert_eq!(unknown.raw, serde_json::json!({"type": "pipe", "name": "p"}));
+				}
+				child => panic!("unexpected child {:?}", child),
+			}
+		}
+	}
+
+	#[test]
+	fn number_or_string_accepts_both()
+	{
+		for fmax in [serde_json::json!("312.5"), serde_json::json!(312.5)].iter()
+		{
+			match serde_json::from_value::<Node>(run_information(fmax.clone())).unwrap()
+			{
+				Node::RunInformation(run_information) => assert_eq!(run_information.fmax, 312.5),
+				node => panic!("unexpected node {:?}", node),
+			}
+		}
+
+		let is_autorun = |value: serde_json::Value|
+			{
+				#[serde_with::serde_as]
+				#[derive(serde::Deserialize)]
+				struct Flag(#[serde_as(as = "NumberOrString")] bool);
+
+				serde_json::from_value::<Flag>(value).map(|flag| flag.0)
+			};
+
+		assert!(is_autorun(serde_json::json!("true")).unwrap());
+		assert!(!is_autorun(serde_json::json!(false)).unwrap());
+		assert!(serde_json::from_value::<Node>(run_information("fast".into())).is_err());
+	}
+
+	#[test]
+	fn empty_string_is_an_empty_array()
+	{
+		let nodes: Nodes = serde_json::from_value(serde_json::json!({"nodes": ""})).unwrap();
+		assert!(nodes.nodes.is_empty());
+
+		let nodes: Nodes = serde_json::from_value(serde_json::json!({"nodes": [run_information(1.into())]}))
+			.unwrap();
+		assert_eq!(nodes.nodes.len(), 1);
+
+		assert!(serde_json::from_value::<Nodes>(serde_json::json!({"nodes": "none"})).is_err());
+	}
+}
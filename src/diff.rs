@@ -0,0 +1,206 @@
+use crate::data_model::FileReference;
+use crate::metrics::*;
+use crate::report::*;
+
+/// Identifies a module instance across profiles
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InstanceKey
+{
+	pub source_files: Vec<FileReference>,
+	pub kernel: String,
+	/// Unroll instance (1-based), only present for a per instance comparison
+	pub instance: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricDelta
+{
+	pub metric: Metric,
+	pub baseline: f32,
+	pub candidate: f32,
+}
+
+impl MetricDelta
+{
+	pub fn absolute(&self) -> f32
+	{
+		self.candidate - self.baseline
+	}
+
+	/// Relative change in %, if the baseline is non-zero
+	pub fn relative(&self) -> Option<f32>
+	{
+		(self.baseline != 0.).then(|| self.absolute() / self.baseline.abs() * 100.)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceDiff
+{
+	pub key: InstanceKey,
+	pub deltas: Vec<MetricDelta>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SectionDiff
+{
+	pub matched: Vec<InstanceDiff>,
+	/// Module instances the compiler removed in the candidate
+	pub only_in_baseline: Vec<InstanceKey>,
+	/// Module instances the compiler added in the candidate
+	pub only_in_candidate: Vec<InstanceKey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diff
+{
+	pub global_memory: SectionDiff,
+	pub local_memory: SectionDiff,
+	pub channel: SectionDiff,
+	pub loops: SectionDiff,
+}
+
+impl Diff
+{
+	/// Compares two reports which need to be expanded, `per_instance` additionally tells the
+	/// unroll instances of a kernel apart
+	pub fn new(baseline: &Report, candidate: &Report, per_instance: bool) -> Self
+	{
+		Diff
+		{
+			global_memory: diff_module_instances(&baseline.global_memory,
+				&candidate.global_memory, per_instance),
+			local_memory: diff_module_instances(&baseline.local_memory,
+				&candidate.local_memory, per_instance),
+			channel: diff_module_instances(&baseline.channel, &candidate.channel, per_instance),
+			loops: diff_module_instances(&baseline.loops, &candidate.loops, per_instance),
+		}
+	}
+}
+
+fn diff_module_instances<Metrics>(baseline: &[ModuleInstanceGroup<Metrics>],
+	candidate: &[ModuleInstanceGroup<Metrics>], per_instance: bool) -> SectionDiff
+where
+	Metrics: MetricValues,
+{
+	let baseline = keyed_metrics(baseline, per_instance);
+	let mut candidate = keyed_metrics(candidate, per_instance);
+
+	let mut section_diff = SectionDiff::default();
+
+	for (key, baseline_metrics) in baseline.into_iter()
+	{
+		match candidate.remove(&key)
+		{
+			Some(candidate_metrics) =>
+			{
+				let deltas = Metric::ALL.iter()
+					.filter_map(|&metric|
+						baseline_metrics.value(metric)
+							.zip(candidate_metrics.value(metric))
+							.map(|(baseline, candidate)| MetricDelta { metric, baseline, candidate }))
+					.collect();
+
+				section_diff.matched.push(InstanceDiff { key, deltas });
+			}
+			None => section_diff.only_in_baseline.push(key),
+		}
+	}
+
+	section_diff.only_in_candidate = candidate.into_keys().collect();
+
+	section_diff
+}
+
+fn keyed_metrics<Metrics>(groups: &[ModuleInstanceGroup<Metrics>], per_instance: bool)
+	-> std::collections::BTreeMap<InstanceKey, &Metrics>
+{
+	groups.iter()
+		.flat_map(|group|
+			group.kernels.iter().flatten()
+				.flat_map(move |kernel|
+				{
+					let key = move |instance|
+						InstanceKey
+						{
+							source_files: group.source_files.clone(),
+							kernel: kernel.name.clone(),
+							instance,
+						};
+
+					if per_instance
+					{
+						kernel.instances.iter().enumerate()
+							.map(|(id, metrics)| (key(Some(id + 1)), metrics))
+							.collect::<Vec<_>>()
+					}
+					else
+					{
+						vec![(key(None), &kernel.metrics)]
+					}
+				}))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn report(profile: serde_json::Value) -> Report
+	{
+		Report::new(&fixtures::load(profile), &Settings { expand: true, ..Default::default() })
+	}
+
+	/// The global memory read is always occupied, the second loop unroll instance was removed and
+	/// a local memory access added
+	fn candidate() -> serde_json::Value
+	{
+		let mut candidate = fixtures::example();
+		let children = candidate["kernels"]["nodes"][0]["children"].as_array_mut().unwrap();
+
+		children[0]["module_inst_details"] = fixtures::details("__global", &[1000, 1000]);
+		children[2] = fixtures::module_instance("local", "/src/kernel.cpp", 30,
+			fixtures::details("__local", &[0, 0]));
+
+		candidate
+	}
+
+	#[test]
+	fn diff_matches_by_source_location_and_kernel()
+	{
+		let diff = Diff::new(&report(fixtures::example()), &report(candidate()), false);
+
+		let occupancy = diff.global_memory.matched[0].deltas.iter()
+			.find(|delta| delta.metric == Metric::Occupancy)
+			.unwrap();
+		assert_eq!((occupancy.baseline, occupancy.candidate), (75., 100.));
+		assert_eq!(occupancy.absolute(), 25.);
+
+		assert_eq!(diff.loops.matched.len(), 1);
+		assert_eq!(diff.loops.matched[0].deltas[0].absolute(), 50.);
+		assert_eq!(diff.loops.matched[0].deltas[0].relative(), Some(100.));
+		assert!(diff.loops.only_in_baseline.is_empty());
+
+		assert_eq!(diff.local_memory.only_in_candidate, vec![InstanceKey
+		{
+			source_files: report(candidate()).local_memory[0].source_files.clone(),
+			kernel: "K".to_owned(),
+			instance: None,
+		}]);
+	}
+
+	#[test]
+	fn diff_per_instance_tells_the_unroll_instances_apart()
+	{
+		let diff = Diff::new(&report(fixtures::example()), &report(candidate()), true);
+
+		assert_eq!(diff.loops.matched.iter().map(|matched| matched.key.instance).collect::<Vec<_>>(),
+			vec![Some(1)]);
+		assert_eq!(diff.loops.matched[0].deltas[0].absolute(), 0.);
+		assert_eq!(diff.loops.only_in_baseline.iter().map(|key| key.instance).collect::<Vec<_>>(),
+			vec![Some(2)]);
+		assert_eq!(MetricDelta { metric: Metric::Stall, baseline: 0., candidate: 5. }.relative(), None);
+	}
+}
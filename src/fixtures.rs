@@ -0,0 +1,146 @@
+//! Profiles for the tests, built as JSON in the layout of the profiler, with plain numbers instead
+//! of strings
+
+use crate::data_model::Profile;
+use serde_json::{json, Value};
+
+/// Profile of the kernel invocations, the other sections are empty and may be replaced
+pub fn profile(kernels: Vec<Value>) -> Value
+{
+	json!({
+		"json_type": "profiler",
+		"versions": {"profiler_json_version": "2021.2.0.268.1", "aocx_version": "21.4"},
+		"kernels": {"nodes": kernels},
+		"boards": {"nodes": ""},
+		"memtransfers": {"nodes": ""},
+		"channels": {"nodes": ""},
+		"run_info": {"nodes": ""},
+	})
+}
+
+/// Profile of an invocation of the kernel `K` from 0 to 2000 ns with a global memory read at
+/// `kernel.cpp:10`, called from `main.cpp:3`, which is 75 % occupied and has a bandwidth of
+/// 750 MB/s, and a loop at `kernel.cpp:20`, whose one unroll instance is always and whose other
+/// one is never occupied
+pub fn example() -> Value
+{
+	let mut global = module_instance("global", "/src/kernel.cpp", 10, details("__global", &[500, 1000]));
+	global["sourcefiles"][0]["callsite"] = json!([{"filename": "main.cpp", "line": 3}]);
+	global["module_inst_details"]["cache_hit_samples"] = json!([250, 500]);
+
+	profile(vec![kernel("K", 0, &[1000, 2000], vec![
+		global,
+		module_instance("loop0", "/src/kernel.cpp", 20, details("__loop", &[1000, 1000])),
+		module_instance("loop1", "/src/kernel.cpp", 20, details("__loop", &[0, 0])),
+	])])
+}
+
+pub fn load(profile: Value) -> Profile
+{
+	serde_json::from_value(profile).unwrap()
+}
+
+/// Kernel invocation ending with its last sample, one cycle per nanosecond, none for intervals out
+/// of order
+pub fn kernel(name: &str, start_time: u64, sample_timestamps: &[u64], children: Vec<Value>) -> Value
+{
+	let cycles = std::iter::once(&start_time)
+		.chain(sample_timestamps.iter())
+		.zip(sample_timestamps.iter())
+		.map(|(start_time, end_time)| end_time.saturating_sub(*start_time))
+		.collect::<Vec<_>>();
+
+	json!({
+		"type": "kernel",
+		"name": name,
+		"compute_unit": 0,
+		"sourcefile": {"filename": "/src/kernel.cpp", "line": 1},
+		"device_ids": [0],
+		"command_queue_ids": [0],
+		"start_time": start_time,
+		"end_time": sample_timestamps.last().copied().unwrap_or(start_time),
+		"num_samples": sample_timestamps.len(),
+		"shared_counter_run_type": 0,
+		"sample_timestamps": sample_timestamps,
+		"total_cycles_between_samples": cycles,
+		"is_autorun": false,
+		"children": children,
+	})
+}
+
+pub fn module_instance(name: &str, file_name: &str, line: u32, details: Value) -> Value
+{
+	json!({
+		"type": "moduleinst",
+		"name": name,
+		"sourcefiles": [{"filename": file_name, "line": line}],
+		"module_inst_details": details,
+	})
+}
+
+/// Details of a module instance of the memory type with every sample vector derived from the
+/// occupancy: the stall is half of it, the idle a quarter, the activity, bandwidth, burst size and
+/// maximum channel depth equal it, the cache hits are a third and the efficiency and average
+/// channel depth a thousandth
+pub fn details(mem_type: &str, occupancy: &[u64]) -> Value
+{
+	let map = |function: fn(u64) -> Value|
+		occupancy.iter().map(|&sample| function(sample)).collect::<Vec<_>>();
+
+	match mem_type
+	{
+		"__global" => json!({
+			"mem_type": mem_type,
+			"operation_type": "read",
+			"occupancy_samples": occupancy,
+			"stall_samples": map(|sample| json!(sample / 2)),
+			"idle_samples": map(|sample| json!(sample / 4)),
+			"activity_samples": occupancy,
+			"bandwidth_samples": occupancy,
+			"bandwidth_eff_samples": map(|sample| json!(sample as f32 / 1000.)),
+			"cache_hit_samples": map(|sample| json!(sample / 3)),
+			"coalesced_memory": false,
+			"global_mem_name": "DDR",
+			"average_burst_size": occupancy,
+		}),
+		"__local" => json!({
+			"mem_type": mem_type,
+			"operation_type": "write",
+			"occupancy_samples": occupancy,
+			"stall_samples": map(|sample| json!(sample / 2)),
+			"idle_samples": map(|sample| json!(sample / 4)),
+			"activity_samples": occupancy,
+			"coalesced_memory": false,
+		}),
+		"__channel" => json!({
+			"mem_type": mem_type,
+			"operation_type": "read",
+			"occupancy_samples": occupancy,
+			"stall_samples": map(|sample| json!(sample / 2)),
+			"idle_samples": map(|sample| json!(sample / 4)),
+			"average_channel_depth_samples": map(|sample| json!(sample as f32 / 1000.)),
+			"max_channel_depth_samples": occupancy,
+			"activity_samples": occupancy,
+			"bandwidth_samples": occupancy,
+			"coalesced_memory": false,
+		}),
+		"__loop" => json!({
+			"mem_type": mem_type,
+			"occupancy_samples": occupancy,
+		}),
+		_ => json!({"mem_type": mem_type}),
+	}
+}
+
+pub fn memory_transfer(type_transfer: &str, device_id: u32, command_queue_id: u32, start_time: u64,
+	end_time: u64) -> Value
+{
+	json!({
+		"type": "memtransfers",
+		"type_transfer": type_transfer,
+		"device_id": device_id,
+		"command_queue_id": command_queue_id,
+		"start_time": start_time,
+		"end_time": end_time,
+	})
+}
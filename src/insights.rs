@@ -0,0 +1,243 @@
+use crate::data_model::FileReference;
+use crate::metrics::*;
+use crate::report::*;
+
+/// Share of stalled cycles (in %) from which on a stall is considered a bottleneck
+const STALL_THRESHOLD: f32 = 10.;
+/// Occupancy (in %) below which a loop is considered a bottleneck
+const LOOP_OCCUPANCY_THRESHOLD: f32 = 50.;
+/// Efficiency (in %) below which a global memory access is considered wasteful
+const EFFICIENCY_THRESHOLD: f32 = 50.;
+/// Share of the maximum burst below which bursts are considered too short
+const BURST_THRESHOLD: f32 = 0.5;
+
+/// Likely bottleneck of a module instance within a kernel
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Insight
+{
+	pub memory_type: &'static str,
+	pub source_files: Vec<FileReference>,
+	pub kernel: String,
+	/// Affected share of the runtime of all considered kernels (in %), to rank the insights
+	pub impact: f32,
+	pub finding: String,
+	pub explanation: String,
+}
+
+/// Ranks the module instances of an expanded report by the impact of their likely bottleneck,
+/// weighted by the share of the kernel in the overall runtime, and returns the highest ranked
+pub fn insights(report: &Report, count: usize) -> Vec<Insight>
+{
+	let total_runtime = report.kernels.iter().map(|kernel| kernel.total_runtime).sum::<u64>();
+
+	let weights = report.kernels.iter()
+		.map(|kernel|
+			(kernel.name.as_str(), kernel.total_runtime as f32 / total_runtime.max(1) as f32))
+		.collect::<std::collections::BTreeMap<_, _>>();
+
+	let maximum_burst = report.boards.iter()
+		.flat_map(|board| board.global_memories.iter())
+		.map(|global_memory| global_memory.maximum_burst)
+		.fold(0., f32::max);
+
+	let mut insights = Vec::new();
+
+	collect_insights("global memory", &report.global_memory, &weights, &mut insights,
+		|metrics| global_memory_findings(metrics, maximum_burst));
+	collect_insights("local memory", &report.local_memory, &weights, &mut insights,
+		local_memory_findings);
+	collect_insights("channel", &report.channel, &weights, &mut insights, channel_findings);
+	collect_insights("loop", &report.loops, &weights, &mut insights, loop_findings);
+
+	rank(&mut insights);
+	insights.truncate(count);
+
+	insights
+}
+
+/// Orders the insights by descending impact, NaN last
+fn rank(insights: &mut [Insight])
+{
+	insights.sort_by(|left, right| compare_values(Some(left.impact), Some(right.impact), false));
+}
+
+/// Finding, explanation and impact (in % of the kernel runtime) of each likely bottleneck
+type Finding = (String, String, f32);
+
+fn collect_insights<Metrics, Findings>(memory_type: &'static str, groups: &[ModuleInstanceGroup<Metrics>],
+	weights: &std::collections::BTreeMap<&str, f32>, insights: &mut Vec<Insight>, findings: Findings)
+where
+	Findings: Fn(&Metrics) -> Vec<Finding>,
+{
+	for group in groups.iter()
+	{
+		for kernel in group.kernels.iter().flatten()
+		{
+			let weight = weights.get(kernel.name.as_str()).copied().unwrap_or_default();
+
+			insights.extend(findings(&kernel.metrics).into_iter()
+				.filter(|(_, _, impact)| impact.is_finite())
+				.map(|(finding, explanation, impact)|
+					Insight
+					{
+						memory_type,
+						source_files: group.source_files.clone(),
+						kernel: kernel.name.clone(),
+						impact: impact * weight,
+						finding,
+						explanation,
+					}));
+		}
+	}
+}
+
+fn global_memory_findings(metrics: &GlobalMetrics, maximum_burst: f32) -> Vec<Finding>
+{
+	let mut findings = Vec::new();
+	let occupancy = metrics.occupancy.occupancy / 100.;
+
+	if metrics.stall.stall >= STALL_THRESHOLD
+	{
+		findings.push((
+			format!("stalled {:.1} % of the time", metrics.stall.stall),
+			"the LSU waits for global memory, likely throttled by the available bandwidth or \
+				contention with other LSUs".to_owned(),
+			metrics.stall.stall));
+	}
+
+	if metrics.effectiveness.efficiency < EFFICIENCY_THRESHOLD
+	{
+		findings.push((
+			format!("only {:.1} % of the transferred data is used", metrics.effectiveness.efficiency),
+			"likely unaligned or non-contiguous accesses".to_owned(),
+			(100. - metrics.effectiveness.efficiency) * occupancy));
+	}
+
+	if maximum_burst > 0. && metrics.effectiveness.burst_size < maximum_burst * BURST_THRESHOLD
+	{
+		findings.push((
+			format!("average burst size of {:.1} out of a maximum of {}",
+				metrics.effectiveness.burst_size, maximum_burst),
+			"likely non-coalesced or data-dependent accesses".to_owned(),
+			(1. - metrics.effectiveness.burst_size / maximum_burst) * 100. * occupancy));
+	}
+
+	findings
+}
+
+fn local_memory_findings(metrics: &LocalMetrics) -> Vec<Finding>
+{
+	if metrics.stall.stall < STALL_THRESHOLD
+	{
+		return Vec::new();
+	}
+
+	vec![(
+		format!("stalled {:.1} % of the time", metrics.stall.stall),
+		"likely bank conflicts or arbitration between too many ports".to_owned(),
+		metrics.stall.stall)]
+}
+
+fn channel_findings(metrics: &ChannelMetrics) -> Vec<Finding>
+{
+	if metrics.stall.stall < STALL_THRESHOLD
+	{
+		return Vec::new();
+	}
+
+	let average_depth = metrics.channel_depth.average_channel_depth;
+	let maximum_depth = metrics.channel_depth.maximum_channel_depth as f32;
+
+	let explanation = if maximum_depth > 0. && average_depth >= maximum_depth * 0.9
+	{
+		"the channel is constantly full, the consumer cannot keep up"
+	}
+	else if average_depth <= maximum_depth * 0.1
+	{
+		"the channel is constantly empty, the producer cannot keep up"
+	}
+	else
+	{
+		"producer and consumer run at varying rates, a deeper channel may decouple them"
+	};
+
+	vec![(
+		format!("stalled {:.1} % of the time at an average depth of {:.1} (maximum: {})",
+			metrics.stall.stall, average_depth, metrics.channel_depth.maximum_channel_depth),
+		explanation.to_owned(),
+		metrics.stall.stall)]
+}
+
+fn loop_findings(metrics: &LoopMetrics) -> Vec<Finding>
+{
+	if metrics.occupancy.occupancy >= LOOP_OCCUPANCY_THRESHOLD
+	{
+		return Vec::new();
+	}
+
+	vec![(
+		format!("occupied only {:.1} % of the time", metrics.occupancy.occupancy),
+		"likely limited by a loop-carried dependency (II > 1), a low trip count or stalls of the \
+			loop body".to_owned(),
+		100. - metrics.occupancy.occupancy)]
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn findings(settings: &Settings) -> Vec<(&'static str, String)>
+	{
+		// The loop becomes occupied 10 % of the time
+		let mut profile = fixtures::example();
+		profile["kernels"]["nodes"][0]["children"][1]["module_inst_details"]["occupancy_samples"] =
+			serde_json::json!([200, 200]);
+
+		Report::new(&fixtures::load(profile), settings).insights.into_iter()
+			.map(|insight| (insight.memory_type, insight.finding))
+			.collect()
+	}
+
+	#[test]
+	fn insights_cover_the_selected_sections()
+	{
+		let settings = |sections: Option<Vec<Section>>|
+			Settings { insights: 5, sections, ..Default::default() };
+
+		let all = findings(&settings(None));
+
+		assert_eq!(all, vec![
+			("loop", "occupied only 10.0 % of the time".to_owned()),
+			("global memory", "stalled 37.5 % of the time".to_owned()),
+		]);
+		assert_eq!(findings(&settings(Some(vec![Section::GlobalMemory, Section::Insights]))), all[1..]);
+		assert_eq!(findings(&settings(Some(vec![Section::Kernels, Section::Insights]))), all);
+		assert_eq!(findings(&settings(Some(vec![Section::Loop, Section::Insights]))), all[..1]);
+		assert!(findings(&settings(Some(vec![Section::Loop]))).is_empty());
+	}
+
+	#[test]
+	fn rank_orders_by_descending_impact_with_nan_last()
+	{
+		let insight = |impact: f32|
+			Insight
+			{
+				memory_type: "loop",
+				source_files: Vec::new(),
+				kernel: "K".to_owned(),
+				impact,
+				finding: String::new(),
+				explanation: String::new(),
+			};
+
+		let mut insights = vec![insight(10.), insight(f32::NAN), insight(50.), insight(f32::NAN), insight(20.)];
+		rank(&mut insights);
+
+		let impacts = insights.iter().map(|insight| insight.impact).collect::<Vec<_>>();
+
+		assert_eq!(impacts[..3], [50., 20., 10.]);
+		assert!(impacts[3..].iter().all(|impact| impact.is_nan()));
+	}
+}
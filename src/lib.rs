@@ -0,0 +1,313 @@
+//! Model of the profiles recorded by the Intel FPGA Dynamic Profiler and the aggregation of their
+//! samples into typed metrics.
+//!
+//! [`load_profile`] deserializes a profile into the [`data_model`], which [`report::Report`]
+//! aggregates into the metrics of [`metrics`] per source location, kernel and unroll instance.
+//! Several runs are combined into one profile by [`aggregate::merge`].
+
+pub mod aggregate;
+pub mod annotate;
+pub mod check;
+pub mod data_model;
+pub mod diff;
+#[cfg(test)]
+mod fixtures;
+pub mod insights;
+pub mod metrics;
+pub mod module_instance_details;
+pub mod output;
+pub mod report;
+pub mod series;
+pub mod source_pattern;
+pub mod timeline;
+pub mod trace;
+pub mod validate;
+
+/// Deserializes the profile while it is read, without holding the file content in memory
+pub fn load_profile(path: &std::path::Path) -> anyhow::Result<data_model::Profile>
+{
+	serde_json::from_reader(open_input(path)?).map_err(load_error)
+}
+
+/// Loads the profile with the samples of every kernel invocation folded into their sums right after
+/// the invocation is read, see [`metrics::fold_samples`], hence holds only one invocation's samples
+/// in memory at a time; sufficient for the report, but not for the timeline, the sparklines, the
+/// charts or the validation
+pub fn load_profile_folded(path: &std::path::Path) -> anyhow::Result<data_model::Profile>
+{
+	serde_json::from_reader::<_, data_model::FoldedProfile>(open_input(path)?)
+		.map(|profile| profile.0)
+		.map_err(load_error)
+}
+
+/// Points out that a missing field may have been renamed or removed by another release, as such
+/// changes are not mapped
+fn load_error(error: serde_json::Error) -> anyhow::Error
+{
+	if error.is_data() && error.to_string().starts_with("missing field")
+	{
+		anyhow::anyhow!("{}, which may have been renamed or removed by a profiler release other than the \
+			tested {}", error, data_model::TESTED_RELEASES.join(", "))
+	}
+	else
+	{
+		error.into()
+	}
+}
+
+/// Opens a file, or stdin for `-`, decompressing gzip, zstd and xz by their magic bytes rather than
+/// the extension
+pub fn open_input(path: &std::path::Path) -> anyhow::Result<Box<dyn std::io::BufRead>>
+{
+	open(path, std::io::stdin)
+}
+
+/// Opens the input given by `stdin` for `-`
+fn open<Stdin, Input>(path: &std::path::Path, stdin: Stdin) -> anyhow::Result<Box<dyn std::io::BufRead>>
+where
+	Stdin: FnOnce() -> Input,
+	Input: std::io::Read + 'static,
+{
+	let input: Box<dyn std::io::BufRead> = if path == std::path::Path::new("-")
+	{
+		Box::new(std::io::BufReader::new(stdin()))
+	}
+	else
+	{
+		Box::new(std::io::BufReader::new(std::fs::File::open(path)?))
+	};
+
+	decompress(input)
+}
+
+fn decompress(mut input: Box<dyn std::io::BufRead>) -> anyhow::Result<Box<dyn std::io::BufRead>>
+{
+	use std::io::Read as _;
+
+	const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+	const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+	const XZ_MAGIC: &[u8] = &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+
+	// A single read may return fewer bytes, e.g. from a pipe, hence up to the longest magic is read
+	// until the end and put in front again
+	let mut magic = Vec::with_capacity(XZ_MAGIC.len());
+	(&mut input).take(XZ_MAGIC.len() as u64).read_to_end(&mut magic)?;
+
+	let input = std::io::Cursor::new(magic.clone()).chain(input);
+
+	Ok(if magic.starts_with(GZIP_MAGIC)
+	{
+		Box::new(std::io::BufReader::new(flate2::bufread::MultiGzDecoder::new(input)))
+	}
+	else if magic.starts_with(ZSTD_MAGIC)
+	{
+		Box::new(std::io::BufReader::new(zstd::stream::read::Decoder::with_buffer(input)?))
+	}
+	else if magic.starts_with(XZ_MAGIC)
+	{
+		Box::new(std::io::BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(input)))
+	}
+	else
+	{
+		Box::new(input)
+	})
+}
+
+/// Loads the profiles in the order given, directories contribute their `.json` files (also
+/// compressed as `.json.gz`, `.json.zst` or `.json.xz`) in the order of their names, with their
+/// samples folded if `folded`
+pub fn load_profiles(paths: &[std::path::PathBuf], folded: bool)
+	-> anyhow::Result<Vec<data_model::Profile>>
+{
+	let mut files = Vec::new();
+
+	for path in paths.iter()
+	{
+		if path.is_dir()
+		{
+			let mut entries = std::fs::read_dir(path)?
+				.map(|entry| entry.map(|entry| entry.path()))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			entries.retain(|entry| entry.is_file()
+				&& entry.file_name().and_then(|file_name| file_name.to_str())
+					.map(|file_name| [".json", ".json.gz", ".json.zst", ".json.xz"].iter()
+						.any(|extension| file_name.ends_with(extension)))
+					.unwrap_or(false));
+			entries.sort();
+
+			if entries.is_empty()
+			{
+				anyhow::bail!("no json profile files in {}", path.display());
+			}
+
+			files.extend(entries);
+		}
+		else
+		{
+			files.push(path.clone());
+		}
+	}
+
+	files.iter()
+		.map(|file|
+			{
+				let profile = if folded { load_profile_folded(file) } else { load_profile(file) };
+
+				profile.map_err(|error| anyhow::anyhow!("failed to load {}: {}", file.display(), error))
+			})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+	use std::io::Write as _;
+
+	const CONTENT: &str = "{\"json_type\": \"profiler\"}";
+
+	/// Returns a single byte per read, as a slow pipe might
+	struct Trickle(std::io::Cursor<Vec<u8>>);
+
+	impl std::io::Read for Trickle
+	{
+		fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize>
+		{
+			let length = buffer.len().min(1);
+
+			self.0.read(&mut buffer[..length])
+		}
+	}
+
+	fn trickle(content: Vec<u8>) -> Box<dyn std::io::BufRead>
+	{
+		Box::new(std::io::BufReader::with_capacity(1, Trickle(std::io::Cursor::new(content))))
+	}
+
+	fn read(input: Box<dyn std::io::BufRead>) -> String
+	{
+		let mut content = String::new();
+		std::io::Read::read_to_string(&mut decompress(input).unwrap(), &mut content).unwrap();
+
+		content
+	}
+
+	fn gzip(content: &[u8]) -> Vec<u8>
+	{
+		let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+		gzip.write_all(content).unwrap();
+
+		gzip.finish().unwrap()
+	}
+
+	fn zstd(content: &[u8]) -> Vec<u8>
+	{
+		zstd::encode_all(content, 0).unwrap()
+	}
+
+	fn xz(content: &[u8]) -> Vec<u8>
+	{
+		let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+		xz.write_all(content).unwrap();
+
+		xz.finish().unwrap()
+	}
+
+	#[test]
+	fn decompress_detects_magic_split_over_reads()
+	{
+		assert_eq!(read(trickle(gzip(CONTENT.as_bytes()))), CONTENT);
+		assert_eq!(read(trickle(zstd(CONTENT.as_bytes()))), CONTENT);
+		assert_eq!(read(trickle(xz(CONTENT.as_bytes()))), CONTENT);
+		assert_eq!(read(trickle(CONTENT.as_bytes().to_vec())), CONTENT);
+		assert_eq!(read(trickle(b"{".to_vec())), "{");
+	}
+
+	#[test]
+	fn decompress_reads_concatenated_streams()
+	{
+		let concatenated = |compress: fn(&[u8]) -> Vec<u8>|
+			[compress(b"{\"json_type\": "), compress(b"\"profiler\"}")].concat();
+
+		assert_eq!(read(trickle(concatenated(gzip))), CONTENT);
+		assert_eq!(read(trickle(concatenated(zstd))), CONTENT);
+		assert_eq!(read(trickle(concatenated(xz))), CONTENT);
+	}
+
+	#[test]
+	fn open_reads_stdin_for_a_dash()
+	{
+		let open_content = |path: &str, content: Vec<u8>|
+		{
+			let mut input = open(std::path::Path::new(path),
+				|| Trickle(std::io::Cursor::new(content))).unwrap();
+
+			let mut content = String::new();
+			std::io::Read::read_to_string(&mut input, &mut content).unwrap();
+
+			content
+		};
+
+		assert_eq!(open_content("-", xz(CONTENT.as_bytes())), CONTENT);
+		assert_eq!(open_content("-", CONTENT.as_bytes().to_vec()), CONTENT);
+		assert!(open(std::path::Path::new("/nonexistent/-"), std::io::empty).is_err());
+	}
+
+	#[test]
+	fn load_error_points_out_missing_fields()
+	{
+		let mut profile = fixtures::example();
+		profile["kernels"]["nodes"][0].as_object_mut().unwrap().remove("sample_timestamps");
+
+		let error = load_error(serde_json::from_value::<data_model::Profile>(profile).unwrap_err());
+		assert_eq!(error.to_string(), "missing field `sample_timestamps`, which may have been renamed or \
+			removed by a profiler release other than the tested 2021.2");
+
+		let error = load_error(serde_json::from_str::<data_model::Profile>("{").unwrap_err());
+		assert!(!error.to_string().contains("release"));
+	}
+
+	#[test]
+	fn load_profiles_detects_the_compression_regardless_of_the_extension()
+	{
+		let directory = std::env::temp_dir().join(format!("load-profiles-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+
+		let profile = serde_json::to_vec(&fixtures::example()).unwrap();
+		// Named so that the extension does not match the compression
+		std::fs::write(directory.join("1.json"), gzip(&profile)).unwrap();
+		std::fs::write(directory.join("2.json.gz"), zstd(&profile)).unwrap();
+		std::fs::write(directory.join("3.json.zst"), xz(&profile)).unwrap();
+		std::fs::write(directory.join("4.json.xz"), &profile).unwrap();
+		std::fs::write(directory.join("notes.txt"), "").unwrap();
+
+		let profiles = load_profiles(std::slice::from_ref(&directory), false);
+		let folded = load_profiles(&[directory.join("3.json.zst")], true);
+
+		std::fs::remove_dir_all(&directory).unwrap();
+
+		// Occupancy samples of the first loop, which are gone if folded
+		let loop_occupancy = |profile: &data_model::Profile|
+			match &profile.kernels.nodes[..]
+			{
+				[data_model::Node::Kernel(kernel)] => kernel.children.iter()
+					.find_map(|child|
+						match child
+						{
+							data_model::Child::ModuleInstance(data_model::ModuleInstance
+							{
+								module_instance_details: data_model::ModuleInstanceDetails::Loop(sample), ..
+							}) => Some(sample.occupancy_samples.clone()),
+							_ => None,
+						}),
+				nodes => panic!("unexpected nodes {:?}", nodes),
+			};
+
+		let profiles = profiles.unwrap();
+		assert_eq!(profiles.len(), 4);
+		assert!(profiles.iter().all(|profile| loop_occupancy(profile) == Some(vec![1000, 1000])));
+		assert_eq!(loop_occupancy(&folded.unwrap()[0]), Some(vec![]));
+	}
+}
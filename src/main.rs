@@ -1,545 +1,362 @@
-mod data_model;
-mod module_instance_details;
+mod tui;
 
-use data_model::*;
-use module_instance_details::*;
+use intel_fpga_profile_viewer::*;
 
 #[derive(Debug, structopt::StructOpt)]
 #[structopt(about = env!("CARGO_PKG_DESCRIPTION"))]
 struct Options
 {
-	/// Path to the json profile file
+	/// Paths to the json profile files or directories containing them (required without a
+	/// subcommand), several runs are aggregated into one report
 	#[structopt(parse(from_os_str))]
-	profile_file: std::path::PathBuf,
-	/// Sets the kernels to be considered (all kernels if the option is absent)
-	#[structopt(short, long)]
-	kernels: Option<Vec<String>>,
+	profile_files: Vec<std::path::PathBuf>,
+	/// Sets the kernels to be considered as regular expressions matching the whole name, e.g.
+	/// `.*Producer.*` (all kernels if the option is absent), memory transfers are not attributed to
+	/// kernels and hence always listed in full
+	#[structopt(short, long, parse(try_from_str = report::kernel_pattern), global = true)]
+	kernels: Option<Vec<regex::Regex>>,
+	/// Sets the kernels not to be considered as regular expressions matching the whole name
+	#[structopt(long, parse(try_from_str = report::kernel_pattern), number_of_values = 1, global = true)]
+	exclude_kernels: Vec<regex::Regex>,
+	/// Sets the source locations of the module instances to be considered as
+	/// `<glob>[:<line>[-<line>]]`, matching callsites as well (all if the option is absent)
+	#[structopt(long, value_name = "source", number_of_values = 1, global = true)]
+	source: Vec<source_pattern::SourcePattern>,
 	/// Expands the module instance section to show a more fine-grain breakdown
-	#[structopt(short, long)]
+	#[structopt(short, long, global = true)]
 	expand: bool,
+	/// Sets the number of likely bottlenecks listed in the insights section, which covers the
+	/// selected module instance sections (all if none is selected)
+	#[structopt(long, default_value = "5", global = true)]
+	insights: usize,
+	/// Sets the sections to be printed, e.g. `global,loop` (all sections if the option is absent),
+	/// diff and check always cover every section
+	#[structopt(long, use_delimiter = true, possible_values = report::Section::VARIANTS, global = true)]
+	sections: Option<Vec<report::Section>>,
+	/// Sets the format of the report
+	#[structopt(short, long, default_value = "text", possible_values = output::OutputFormat::VARIANTS)]
+	output: output::OutputFormat,
+	/// Appends a sparkline of the values over time to the metrics of the text output, which keeps
+	/// every sample in memory
+	#[structopt(long, conflicts_with = "timeline")]
+	sparklines: bool,
+	/// Orders the module instances of each section by the metric (descending)
+	#[structopt(long, value_name = "metric", possible_values = metrics::Metric::VARIANTS)]
+	sort_by: Option<metrics::Metric>,
+	/// Orders the module instances in ascending order
+	#[structopt(long, requires = "sort-by")]
+	ascending: bool,
+	/// Lists only the first N module instances of each section
+	#[structopt(long, value_name = "N")]
+	top: Option<usize>,
+	/// Breaks the module instances of aggregated runs down per run, with mean and standard deviation
+	#[structopt(long, conflicts_with = "timeline")]
+	per_run: bool,
+	/// Lists the sampling intervals of the module instances at the source location instead of the
+	/// report, e.g. `kernel.cpp:42`
+	#[structopt(long, value_name = "source")]
+	timeline: Option<source_pattern::SourcePattern>,
+	#[structopt(subcommand)]
+	command: Option<Command>,
 }
 
-fn main() -> anyhow::Result<()>
+#[derive(Debug, structopt::StructOpt)]
+enum Command
 {
-	use structopt::StructOpt as _;
-
-	let options = Options::from_args();
-
-	let file_content = std::fs::read_to_string(&options.profile_file)?;
-	let profile: Profile = serde_json::from_str(&file_content)?;
+	/// Compares the module instances of two profiles, per kernel (per unroll instance if expanded)
+	Diff
+	{
+		/// Path to the json profile file to compare against
+		#[structopt(parse(from_os_str))]
+		baseline_file: std::path::PathBuf,
+		/// Path to the json profile file to be compared
+		#[structopt(parse(from_os_str))]
+		candidate_file: std::path::PathBuf,
+	},
+	/// Evaluates the thresholds of a TOML file against a profile, fails if any is violated
+	Check
+	{
+		/// Path to the TOML file with the thresholds
+		#[structopt(parse(from_os_str))]
+		thresholds_file: std::path::PathBuf,
+		/// Path to the json profile file
+		#[structopt(parse(from_os_str))]
+		profile_file: std::path::PathBuf,
+	},
+	/// Exports the kernel invocations and memory transfers as Chrome trace (e.g. for Perfetto)
+	Trace
+	{
+		/// Adds counter tracks of the sampled bandwidth and occupancy of the module instances
+		#[structopt(short, long)]
+		counters: bool,
+		/// Path to the json profile file
+		#[structopt(parse(from_os_str))]
+		profile_file: std::path::PathBuf,
+	},
+	/// Prints a source file with the metrics of the module instances originating from each line
+	Annotate
+	{
+		/// Directories to look for the source file if it is neither found as given nor at the
+		/// path referenced by the profile
+		#[structopt(short = "I", long, parse(from_os_str), number_of_values = 1)]
+		search_path: Vec<std::path::PathBuf>,
+		/// Path or file name of the source file
+		#[structopt(parse(from_os_str))]
+		source_file: std::path::PathBuf,
+		/// Path to the json profile file
+		#[structopt(parse(from_os_str))]
+		profile_file: std::path::PathBuf,
+	},
+	/// Checks a profile for internal consistency, e.g. the lengths of the sample vectors, fails if
+	/// any issue is found
+	Validate
+	{
+		/// Path to the json profile file
+		#[structopt(parse(from_os_str))]
+		profile_file: std::path::PathBuf,
+	},
+	/// Browses the module instances of a profile in an interactive terminal user interface
+	Tui
+	{
+		/// Path to the json profile file
+		#[structopt(parse(from_os_str))]
+		profile_file: std::path::PathBuf,
+	},
+}
 
-	println!("{}: {} (aocx: {})",
-		profile.json_type,
-		profile.versions.profiler_json_version,
-		profile.versions.aocx_version);
+fn main() -> anyhow::Result<std::process::ExitCode>
+{
+	use structopt::StructOpt as _;
 
-	print_boards(&profile);
+	let mut warnings = Vec::new();
 
-	print_run_information(&profile);
-	print_external_memory(&profile, &options);
+	let result = run(Options::from_args(), &mut warnings);
 
-	print_global_memory_module_instances(&profile, &options);
-	print_local_memory_module_instances(&profile, &options);
-	print_channel_module_instances(&profile, &options);
-	print_loop_module_instances(&profile, &options);
+	// Summarized at the end, as they would get lost in front of a long report
+	write_warnings(&mut std::io::stderr().lock(), &warnings)?;
 
-	Ok(())
+	result
 }
 
-fn print_boards(profile: &Profile)
+/// Loads the profile, with its samples folded if only the report is needed, and collects its
+/// deviations from the tested profiles
+fn load(path: &std::path::Path, folded: bool, warnings: &mut Vec<data_model::Warning>)
+	-> anyhow::Result<data_model::Profile>
 {
-	let boards = profile.boards.nodes.iter()
-		.filter_map(|node|
-			match node
-			{
-				Node::Board(board) => Some(board),
-				_ => None
-			})
-		.map(|board|
-			{
-				let global_memories = board.children.iter()
-					.filter_map(|child|
-						match child
-						{
-							Child::GlobalMemory(global_memory) => Some(global_memory),
-							_ => None
-						});
-
-				(&board.board_type, global_memories)
-			});
+	let profile = if folded { load_profile_folded(path)? } else { load_profile(path)? };
 
-	println!("Boards:");
+	warnings.extend(profile.warnings());
 
-	for (board_type, global_memories) in boards
-	{
-		println!("\tType: {}", board_type);
-		println!("\tGlobal memory:");
-
-		for global_memory in global_memories
-		{
-			println!("\t\tMemory {}:", global_memory.global_memory_name);
-			println!("\t\t\tMaximum theoretical global bandwidth: {} MB/s",
-				global_memory.maximum_theoretical_global_memory_bandwidth);
-			println!("\t\t\tMaximum burst: {}", global_memory.maximum_burst_count);
-		}
-	}
+	Ok(profile)
 }
 
-fn print_run_information(profile: &Profile)
+/// Lists every distinct warning once with the number of its occurrences
+fn write_warnings<Writer: std::io::Write>(writer: &mut Writer, warnings: &[data_model::Warning])
+	-> std::io::Result<()>
 {
-	let run_informations = profile.run_information.nodes.iter()
-		.filter_map(|node|
-			match node
-			{
-				Node::RunInformation(run_information) => Some(run_information),
-				_ => None
-			});
-
-	println!("Run information:");
-
-	for run_information in run_informations
+	if warnings.is_empty()
 	{
-		println!("\tFmax: {} MHz", run_information.fmax);
+		return Ok(());
 	}
-}
 
-fn print_external_memory(profile: &Profile, options: &Options)
-{
-	let structured_bandwidths = profile.kernels.nodes.iter()
-		.filter_map(|node|
-			match node
-			{
-				Node::Kernel(kernel) => Some(kernel),
-				_ => None
-			})
-		.filter(|kernel|
-			options.kernels.as_ref().map(|kernels| kernels.contains(&kernel.name)).unwrap_or(true))
-		.flat_map(|kernel|
-			kernel.children.iter()
-				.filter_map(move |child|
-					match child
-					{
-						Child::ExternalMemory(external_memory) => Some((kernel, external_memory)),
-						_ => None
-					}))
-		.map(|(kernel, external_memory)|
-			{
-				let intervals = std::iter::once(&kernel.start_time)
-					.chain(kernel.sample_timestamps.iter())
-					.zip(kernel.sample_timestamps.iter());
-
-				let samples = external_memory.global_used_bandwidth.iter()
-					.zip(external_memory.average_write_burst.iter())
-					.zip(external_memory.average_read_burst.iter());
-
-				let (bandwidth, write_burst, read_burst) = intervals.zip(samples)
-					.fold((0., 0., 0.),
-						|(bandwidth_sum, write_burst_sum, read_burst_sum),
-							((start_time, end_time), ((bandwidth, write_burst), read_burst))|
-						{
-							let time = (end_time - start_time) as f32;
-
-							(bandwidth_sum + time * bandwidth,
-								write_burst_sum + time * write_burst,
-								read_burst_sum + time * read_burst)
-						});
-
-				let time = kernel.end_time - kernel.start_time;
-
-				((&external_memory.name, &external_memory.port), time, bandwidth,
-					write_burst, read_burst)
-			})
-		.fold(std::collections::BTreeMap::new(),
-			|mut map, ((name, port), time, bandwidth, write_burst, read_burst)|
+	let counts = warnings.iter()
+		.fold(std::collections::BTreeMap::new(), |mut map, warning|
 			{
-				let (ref mut time_sum,
-					(ref mut bandwidth_sum, ref mut write_burst_sum, ref mut read_burst_sum)) =
-						map.entry(name).or_insert_with(std::collections::BTreeMap::new)
-							.entry(port).or_insert((0, (0., 0., 0.)));
-
-				*time_sum += time;
-				*bandwidth_sum += bandwidth;
-				*write_burst_sum += write_burst;
-				*read_burst_sum += read_burst;
+				*map.entry(warning).or_insert(0) += 1;
 
 				map
 			});
 
-	println!("External memory:");
+	writeln!(writer, "Warnings:")?;
 
-	for (name, ports) in structured_bandwidths.into_iter()
+	for (warning, count) in counts.into_iter()
 	{
-		println!("\tMemory {}:", name);
-
-		for (port, (time_sum, (bandwidth_sum, write_burst_sum, read_burst_sum)))
-			in ports.into_iter()
+		match count
 		{
-			println!("\t\tPort {}:", port);
-			println!("\t\t\tBandwidth: {:.2} MB/s", bandwidth_sum / time_sum as f32);
-			println!("\t\t\tWrite burst: {:.2}", write_burst_sum / time_sum as f32);
-			println!("\t\t\tRead burst: {:.2}", read_burst_sum / time_sum as f32);
+			1 => writeln!(writer, "\t{}", warning)?,
+			_ => writeln!(writer, "\t{} ({} times)", warning, count)?,
 		}
 	}
+
+	Ok(())
 }
 
-fn print_module_instances<'a, Filter, Compute, Type>(profile: &'a Profile, options: &Options,
-	filter: Filter, compute: Compute)
-where
-	Filter: Fn(&'a ModuleInstanceDetails) -> Option<&'a Type>,
-	Compute: for<'b> Fn(&'b [(&'a data_model::Kernel, &'a Type)]) -> String,
-	Type: 'a,
+fn run(options: Options, warnings: &mut Vec<data_model::Warning>) -> anyhow::Result<std::process::ExitCode>
 {
-	let structured_samples = profile.kernels.nodes.iter()
-		.filter_map(|node|
-			match node
-			{
-				Node::Kernel(kernel) => Some(kernel),
-				_ => None
-			})
-		.filter(|kernel|
-			options.kernels.as_ref().map(|kernels| kernels.contains(&kernel.name)).unwrap_or(true))
-		.flat_map(|kernel|
-			kernel.children.iter()
-				.filter_map(|child|
-					match child
-					{
-						Child::ModuleInstance(module_instance) => Some(module_instance),
-						_ => None
-					})
-				.filter_map(|module_instance| filter(&module_instance.module_instance_details)
-					.map(|module_instance_details| (module_instance, module_instance_details)))
-				.map(move |(module_instance, module_instance_details)|
-					(kernel, module_instance, module_instance_details)))
-		.fold(std::collections::BTreeMap::new(), |mut map, (kernel, module_instance, sample)|
-			{
-				map.entry(&module_instance.source_files).or_insert_with(Vec::new)
-					.push((kernel, module_instance, sample));
+	let mut settings = report::Settings
+	{
+		kernels: options.kernels,
+		excluded_kernels: options.exclude_kernels,
+		sources: options.source,
+		expand: options.expand,
+		insights: options.insights,
+		sections: options.sections,
+		..Default::default()
+	};
+
+	match (options.command, options.profile_files.as_slice())
+	{
+		(Some(Command::Diff { baseline_file, candidate_file }), _) =>
+		{
+			let per_instance = settings.expand;
+			settings.expand = true;
+			settings.sections = None;
 
-				map
-			});
+			let baseline = report::Report::new(&load(&baseline_file, true, warnings)?, &settings);
+			let candidate = report::Report::new(&load(&candidate_file, true, warnings)?, &settings);
 
+			let diff = diff::Diff::new(&baseline, &candidate, per_instance);
 
-	for (source_files, samples) in structured_samples.into_iter()
-	{
-		println!("{}:", format_file_references(source_files, 0).lines()
-			.map(|line| format!("\t{}", line))
-			.collect::<Vec<_>>().join("\n"));
+			output::write_diff(&mut std::io::stdout().lock(), &diff)?;
 
-		if options.expand
+			Ok(std::process::ExitCode::SUCCESS)
+		}
+		(Some(Command::Check { thresholds_file, profile_file }), _) =>
 		{
-			let structured_samples = samples.into_iter()
-				.fold(std::collections::BTreeMap::new(),
-					|mut map, (kernel, module_instance, sample)|
-					{
-						map.entry(&kernel.name).or_insert_with(std::collections::BTreeMap::new)
-							.entry(&module_instance.name).or_insert_with(Vec::new)
-								.push((kernel, sample));
+			let thresholds = check::Thresholds::load(&thresholds_file)?;
+
+			// Thresholds of excluded sections would otherwise be satisfied silently
+			settings.expand = true;
+			settings.sections = None;
+
+			let report = report::Report::new(&load(&profile_file, true, warnings)?, &settings);
 
-						map
-					});
+			let violations = thresholds.evaluate(&report);
 
-			for (kernal_name, unrolls) in structured_samples.into_iter()
+			if violations.is_empty()
 			{
-				println!("\t\tKernel {}:", kernal_name);
+				println!("All {} thresholds satisfied", thresholds.thresholds.len());
 
-				if unrolls.len() > 1
-				{
-					for (id, (_, samples)) in unrolls.into_iter().enumerate()
-					{
-						println!("\t\t\tInstance {}:", id + 1);
+				Ok(std::process::ExitCode::SUCCESS)
+			}
+			else
+			{
+				println!("Violations:");
 
-						for line in compute(&samples).lines()
-						{
-							println!("\t\t\t\t{}", line);
-						}
-					}
-				}
-				else if let Some((_, samples)) = unrolls.iter().next()
+				for violation in violations.iter()
 				{
-					for line in compute(&samples).lines()
-					{
-						println!("\t\t\t{}", line);
-					}
+					println!("\t{}", violation);
 				}
-			}
-		}
-		else
-		{
-			let samples = samples.into_iter()
-				.map(|(kernel, _, sample)| (kernel, sample))
-				.collect::<Vec<_>>();
 
-			for line in compute(&samples).lines()
-			{
-				println!("\t\t{}", line);
+				Ok(std::process::ExitCode::FAILURE)
 			}
 		}
-	}
-}
-
-fn format_file_references(file_references: &[FileReference], level: usize) -> String
-{
-	let preamble = (level > 0)
-		.then(||
-			{
-				let indentation = std::iter::repeat(' ').take((level - 1) * 2).collect::<String>();
-
-				format!("\n{}⮤ ", indentation)
-			})
-		.unwrap_or_default();
+		(Some(Command::Trace { counters, profile_file }), _) =>
+		{
+			let trace = trace::trace(&load(&profile_file, false, warnings)?, &settings, counters);
 
-	file_references.iter()
-		.map(|file_reference|
-			{
-				let callsite = format_file_references(&file_reference.callsite, level + 1);
-
-				format!("{}{} (line: {}{}){}",
-					preamble,
-					file_reference.file_name.clone().into_os_string().into_string().unwrap(),
-					file_reference.line,
-					file_reference.column_number
-						.map(|column| format!(", column: {}", column))
-						.unwrap_or_default(),
-					callsite)
-			})
-		.collect::<Vec<_>>().join("\n")
-}
+			serde_json::to_writer(std::io::stdout().lock(), &trace)?;
 
-fn print_global_memory_module_instances(profile: &Profile, options: &Options)
-{
-	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Global>
-	{
-		match module_instance_details
-		{
-			ModuleInstanceDetails::Global(ref sample) => Some(sample),
-			_ => None
+			Ok(std::process::ExitCode::SUCCESS)
 		}
-	}
+		(Some(Command::Annotate { search_path, source_file, profile_file }), _) =>
+		{
+			let report = report::Report::new(&load(&profile_file, true, warnings)?, &settings);
 
-	fn compute(samples: &[(&data_model::Kernel, &Global)]) -> String
-	{
-		compute_occupancy(samples.iter()) + "\n"
-			+ compute_stall(samples.iter()).as_ref() + "\n"
-			+ compute_bandwith(samples.iter()).as_ref() + "\n"
-			+ compute_effectiveness(samples.iter()).as_ref()
-	}
+			let path = annotate::resolve_source_file(&source_file, &report, &search_path)
+				.ok_or_else(|| anyhow::anyhow!("source file {} not found", source_file.display()))?;
+			let source = std::fs::read_to_string(path)?;
 
-	println!("Global memory:");
+			let annotations = annotate::annotations(&report, &source_file);
 
-	print_module_instances(profile, options, filter, compute);
-}
+			annotate::write(&mut std::io::stdout().lock(), &source, &annotations)?;
 
-fn print_local_memory_module_instances(profile: &Profile, options: &Options)
-{
-	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Local>
-	{
-		match module_instance_details
-		{
-			ModuleInstanceDetails::Local(ref sample) => Some(sample),
-			_ => None
+			Ok(std::process::ExitCode::SUCCESS)
 		}
-	}
+		(Some(Command::Validate { profile_file }), _) =>
+		{
+			let issues = validate::validate(&load(&profile_file, false, warnings)?);
 
-	fn compute(samples: &[(&data_model::Kernel, &Local)]) -> String
-	{
-		compute_occupancy(samples.iter()) + "\n"
-			+ compute_stall(samples.iter()).as_ref() + "\n"
-	}
+			if issues.is_empty()
+			{
+				println!("No issues found");
 
-	println!("Local memory:");
+				Ok(std::process::ExitCode::SUCCESS)
+			}
+			else
+			{
+				println!("Issues:");
 
-	print_module_instances(profile, options, filter, compute);
-}
+				for issue in issues.iter()
+				{
+					println!("\t{}", issue);
+				}
 
-fn print_channel_module_instances(profile: &Profile, options: &Options)
-{
-	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Channel>
-	{
-		match module_instance_details
-		{
-			ModuleInstanceDetails::Channel(ref sample) => Some(sample),
-			_ => None
+				Ok(std::process::ExitCode::FAILURE)
+			}
 		}
-	}
-
-	fn compute(samples: &[(&data_model::Kernel, &Channel)]) -> String
-	{
-		compute_occupancy(samples.iter()) + "\n"
-			+ compute_stall(samples.iter()).as_ref() + "\n"
-			+ compute_bandwith(samples.iter()).as_ref() + "\n"
-			+ compute_channel_depth(samples.iter()).as_ref()
-	}
+		(Some(Command::Tui { profile_file }), _) =>
+		{
+			settings.expand = true;
 
-	println!("Channel:");
+			let report = report::Report::new(&load(&profile_file, true, warnings)?, &settings);
 
-	print_module_instances(profile, options, filter, compute);
-}
+			tui::run(&report)?;
 
-fn print_loop_module_instances(profile: &Profile, options: &Options)
-{
-	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Loop>
-	{
-		match module_instance_details
-		{
-			ModuleInstanceDetails::Loop(ref sample) => Some(sample),
-			_ => None
+			Ok(std::process::ExitCode::SUCCESS)
 		}
-	}
+		(None, []) => structopt::clap::Error::with_description(
+			"At least one profile file is required without a subcommand",
+			structopt::clap::ErrorKind::MissingRequiredArgument).exit(),
+		(None, profile_files) =>
+		{
+			if options.sparklines && options.output != output::OutputFormat::Text
+			{
+				structopt::clap::Error::with_description("--sparklines requires --output text",
+					structopt::clap::ErrorKind::ArgumentConflict).exit();
+			}
 
-	fn compute(samples: &[(&data_model::Kernel, &Loop)]) -> String
-	{
-		compute_occupancy(samples.iter())
-	}
+			// Without the timeline, the sparklines and the charts of the HTML output the sums of the
+			// samples suffice
+			let folded = options.timeline.is_none() && !options.sparklines
+				&& options.output != output::OutputFormat::Html;
 
-	println!("Loop:");
+			let profiles = load_profiles(profile_files, folded)?;
 
-	print_module_instances(profile, options, filter, compute);
-}
+			for profile in profiles.iter()
+			{
+				warnings.extend(profile.warnings());
+			}
 
-fn compute_occupancy<'a, Samples, Sample>(samples: Samples) -> String
-where
-	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
-	Sample: Occupancy + 'a,
-{
-	let (occupancy_sum, cycles_sum) = samples
-		.flat_map(|(kernel, sample)|
-			sample.occupancy_samples().iter()
-				.zip(kernel.total_cycles_between_samples.iter().flatten()))
-		.fold((0u64, 0u64), |(occupancy_sum, cycles_sum), (&occupancy, &cycles)|
-			(occupancy_sum + occupancy, cycles_sum + cycles));
-
-	format!("Occupancy: {:.2} %", occupancy_sum as f32 / cycles_sum as f32 * 100.)
-}
+			// The runs are reported before merging, as the merge consumes the profiles
+			let runs = options.per_run.then(|| report::Report::runs(&profiles, &settings));
 
-fn compute_stall<'a, Samples, Sample>(samples: Samples) -> String
-where
-	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
-	Sample: Stall + 'a,
-{
-	let (stall_sum, idle_sum, acitvity_sum, cycles_sum) = samples
-		.flat_map(|(kernel, sample)|
-			sample.stall_samples().iter()
-				.zip(sample.idle_samples().iter())
-				.zip(sample.activity_samples().iter())
-				.zip(kernel.total_cycles_between_samples.iter().flatten()))
-		.fold((0u64, 0u64, 0u64, 0u64),
-			|(stall_sum, idle_sum, activity_sum, cycles_sum),
-				(((&stall, &idle), &activity), &cycles)|
-					(stall_sum + stall,
-						idle_sum + idle,
-						activity_sum + activity,
-						cycles_sum + cycles));
-
-	format!("Stall: {:.2} %\nIdle: {:.2} %\nAcitivity: {:.2} %",
-		stall_sum as f32 / cycles_sum as f32 * 100.,
-		idle_sum as f32 / cycles_sum as f32 * 100.,
-		acitvity_sum as f32 / cycles_sum as f32 * 100.)
-}
+			let profile = aggregate::merge(profiles)
+				.ok_or_else(|| anyhow::anyhow!("no profile files given"))?;
 
-fn compute_bandwith<'a, Samples, Sample>(samples: Samples) -> String
-where
-	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)> + Clone,
-	Sample: Bandwidth + 'a,
-{
-	let bandwidth_sum = samples.clone()
-		.map(|(kernel, sample)|
+			match options.timeline
 			{
-				let intervals = std::iter::once(&kernel.start_time)
-					.chain(kernel.sample_timestamps.iter())
-					.zip(kernel.sample_timestamps.iter());
-
-				intervals.zip(sample.bandwidth_samples().iter())
-					.map(|((start_time, end_time), bandwidth)|
-						{
-							(end_time - start_time) as f32 * bandwidth
-						})
-					.sum::<f32>()
-			})
-		.sum::<f32>();
-
-	let total_runtime = samples
-		.map(|(kernel, _)| (kernel.start_time, kernel.end_time))
-		.collect::<std::collections::BTreeSet<_>>()
-		.into_iter()
-		.fold(Vec::<(u64, u64)>::new(), |mut stack, (start_time, end_time)|
-			{
-				let extend = stack.iter_mut().rev().next()
-					.filter(|(_, latest_end_time)| start_time <= *latest_end_time);
-
-				if let Some((_, latest_end_time)) = extend
+				Some(source) =>
 				{
-					*latest_end_time = end_time;
+					let timelines = timeline::timelines(&profile, &settings, &source);
+
+					if timelines.is_empty()
+					{
+						anyhow::bail!("no module instance at {}", source);
+					}
+
+					output::write_timelines(&mut std::io::stdout().lock(), &timelines, options.output)?;
 				}
-				else
+				None =>
 				{
-					stack.push((start_time, end_time));
-				}
+					// Sorting and truncating would interfere with matching module instances, e.g. in
+					// the diff or the check, hence the options only apply to the report
+					settings.sort_by = options.sort_by;
+					settings.ascending = options.ascending;
+					settings.top = options.top;
 
-				stack
-			})
-		.into_iter()
-		.fold(0, |sum, (start_time, end_time)| sum + (end_time - start_time));
+					let mut report = report::Report::new(&profile, &settings);
 
-	format!("Bandwidth: {:.2} MB/s", bandwidth_sum / total_runtime as f32)
-}
+					if let Some(runs) = runs
+					{
+						report.add_runs(&runs);
+					}
 
-fn compute_effectiveness<'a, Samples, Sample>(samples: Samples) -> String
-where
-	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)> + Clone,
-	Sample: Effectiveness + Occupancy + 'a,
-{
-	let (number_of_samples, bandwidth_effective_sum, average_burst_size_sum) = samples.clone()
-		.flat_map(|(_, sample)|
-			sample.bandwidth_effective_samples().iter()
-				.zip(sample.average_burst_size().iter()))
-		.fold((0, 0., 0.),
-			|(number_of_samples, bandwidth_effective_sum, average_burst_size_sum),
-				(&bandwidth_effective, &average_burst_size)|
-					(number_of_samples + 1,
-						bandwidth_effective_sum + bandwidth_effective,
-						average_burst_size_sum + average_burst_size));
-
-	let (cache_hit_samples_present, cache_hit_sum, occupancy_sum) = samples
-		.flat_map(|(_, sample)|
-			sample.cache_hit_samples().iter()
-				.zip(sample.occupancy_samples().iter()))
-		.fold((false, 0u64, 0u64),
-			|(cache_hit_samples_present, cache_hit_sum, occupancy_sum), (&cache_hit, &occupancy)|
-				(cache_hit_samples_present || true,
-					cache_hit_sum + cache_hit, occupancy_sum + occupancy));
-
-	let output = format!("Efficiency: {:.2} %\nBurst size: {:.2}",
-		bandwidth_effective_sum / number_of_samples as f32 * 100.,
-		average_burst_size_sum / number_of_samples as f32);
-
-	if cache_hit_samples_present
-	{
-		format!("{}\nCache hit: {:.2} %", output,
-			cache_hit_sum as f32 / occupancy_sum as f32 * 100.)
-	}
-	else
-	{
-		output
-	}
-}
+					output::write(&mut std::io::stdout().lock(), &profile, &report, &settings, options.output)?;
+				}
+			}
 
-fn compute_channel_depth<'a, Samples, Sample>(samples: Samples) -> String
-where
-	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
-	Sample: ChannelDepth + 'a,
-{
-	let (number_of_samples, average_channel_depth_sum, maximum_channel_depth_overall) = samples
-		.flat_map(|(_, sample)|
-			sample.average_channel_depth_samples().iter()
-				.zip(sample.maximum_channel_depth_samples().iter()))
-		.fold((0, 0., 0),
-			|(number_of_samples, average_channel_depth_sum, maximum_channel_depth_overall),
-				(average_channel_depth, maximum_channel_depth)|
-					(number_of_samples + 1,
-						average_channel_depth_sum + average_channel_depth,
-						maximum_channel_depth_overall.max(*maximum_channel_depth)));
-
-	format!("Channel Depth: {:.2} (maximum: {})",
-		average_channel_depth_sum / number_of_samples as f32,
-		maximum_channel_depth_overall)
+			Ok(std::process::ExitCode::SUCCESS)
+		}
+	}
 }
@@ -0,0 +1,741 @@
+use crate::data_model::*;
+use crate::module_instance_details::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct OccupancyMetrics
+{
+	/// Share of cycles in which the module instance was occupied (in %)
+	pub occupancy: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct StallMetrics
+{
+	/// Share of cycles in which the module instance was stalled (in %)
+	pub stall: f32,
+	/// Share of cycles in which the module instance was idle (in %)
+	pub idle: f32,
+	/// Share of cycles in which the module instance was active (in %)
+	pub activity: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct BandwidthMetrics
+{
+	/// Average bandwidth over the runtime of the involved kernels (in MB/s)
+	pub bandwidth: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct EffectivenessMetrics
+{
+	/// Share of the bandwidth that was effectively used (in %)
+	pub efficiency: f32,
+	pub burst_size: f32,
+	/// Share of the occupied cycles that hit the cache (in %), if a cache is present
+	pub cache_hit: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct ChannelDepthMetrics
+{
+	pub average_channel_depth: f32,
+	pub maximum_channel_depth: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct GlobalMetrics
+{
+	#[serde(flatten)]
+	pub occupancy: OccupancyMetrics,
+	#[serde(flatten)]
+	pub stall: StallMetrics,
+	#[serde(flatten)]
+	pub bandwidth: BandwidthMetrics,
+	#[serde(flatten)]
+	pub effectiveness: EffectivenessMetrics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct LocalMetrics
+{
+	#[serde(flatten)]
+	pub occupancy: OccupancyMetrics,
+	#[serde(flatten)]
+	pub stall: StallMetrics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct ChannelMetrics
+{
+	#[serde(flatten)]
+	pub occupancy: OccupancyMetrics,
+	#[serde(flatten)]
+	pub stall: StallMetrics,
+	#[serde(flatten)]
+	pub bandwidth: BandwidthMetrics,
+	#[serde(flatten)]
+	pub channel_depth: ChannelDepthMetrics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct LoopMetrics
+{
+	#[serde(flatten)]
+	pub occupancy: OccupancyMetrics,
+}
+
+/// Individual value of a metric set, e.g. to address a column or a sort key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Metric
+{
+	Occupancy,
+	Stall,
+	Idle,
+	Activity,
+	Bandwidth,
+	Efficiency,
+	BurstSize,
+	CacheHit,
+	AverageChannelDepth,
+	MaximumChannelDepth,
+}
+
+impl Metric
+{
+	pub const ALL: &'static [Metric] = &[
+		Metric::Occupancy,
+		Metric::Stall,
+		Metric::Idle,
+		Metric::Activity,
+		Metric::Bandwidth,
+		Metric::Efficiency,
+		Metric::BurstSize,
+		Metric::CacheHit,
+		Metric::AverageChannelDepth,
+		Metric::MaximumChannelDepth,
+	];
+
+	pub const VARIANTS: &'static [&'static str] = &["occupancy", "stall", "idle", "activity",
+		"bandwidth", "efficiency", "burst_size", "cache_hit", "average_channel_depth",
+		"maximum_channel_depth"];
+
+	pub fn name(&self) -> &'static str
+	{
+		Self::VARIANTS[Self::ALL.iter().position(|metric| metric == self).unwrap()]
+	}
+
+	/// Human readable name as used in the text report
+	pub fn label(&self) -> &'static str
+	{
+		match self
+		{
+			Metric::Occupancy => "Occupancy",
+			Metric::Stall => "Stall",
+			Metric::Idle => "Idle",
+			Metric::Activity => "Activity",
+			Metric::Bandwidth => "Bandwidth",
+			Metric::Efficiency => "Efficiency",
+			Metric::BurstSize => "Burst size",
+			Metric::CacheHit => "Cache hit",
+			Metric::AverageChannelDepth => "Channel depth",
+			Metric::MaximumChannelDepth => "Maximum channel depth",
+		}
+	}
+
+	pub fn unit(&self) -> &'static str
+	{
+		match self
+		{
+			Metric::Occupancy | Metric::Stall | Metric::Idle | Metric::Activity
+				| Metric::Efficiency | Metric::CacheHit => " %",
+			Metric::Bandwidth => " MB/s",
+			Metric::BurstSize | Metric::AverageChannelDepth | Metric::MaximumChannelDepth => "",
+		}
+	}
+}
+
+impl std::fmt::Display for Metric
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}", self.name())
+	}
+}
+
+impl serde::Serialize for Metric
+{
+	fn serialize<Serializer: serde::Serializer>(&self, serializer: Serializer)
+		-> Result<Serializer::Ok, Serializer::Error>
+	{
+		serializer.serialize_str(self.name())
+	}
+}
+
+impl std::str::FromStr for Metric
+{
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		Self::VARIANTS.iter().position(|name| *name == string)
+			.map(|index| Self::ALL[index])
+			.ok_or_else(|| format!("unknown metric: {}", string))
+	}
+}
+
+/// Uniform access to the values of the different metric sets
+pub trait MetricValues
+{
+	/// Metrics that are part of the set, in the order of `Metric::ALL`
+	const METRICS: &'static [Metric];
+
+	/// Returns the value of the metric if it is part of the set
+	fn value(&self, metric: Metric) -> Option<f32>;
+}
+
+impl MetricValues for GlobalMetrics
+{
+	const METRICS: &'static [Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Idle, Metric::Activity,
+		Metric::Bandwidth, Metric::Efficiency, Metric::BurstSize, Metric::CacheHit];
+
+	fn value(&self, metric: Metric) -> Option<f32>
+	{
+		match metric
+		{
+			Metric::Occupancy => Some(self.occupancy.occupancy),
+			Metric::Stall => Some(self.stall.stall),
+			Metric::Idle => Some(self.stall.idle),
+			Metric::Activity => Some(self.stall.activity),
+			Metric::Bandwidth => Some(self.bandwidth.bandwidth),
+			Metric::Efficiency => Some(self.effectiveness.efficiency),
+			Metric::BurstSize => Some(self.effectiveness.burst_size),
+			Metric::CacheHit => self.effectiveness.cache_hit,
+			_ => None,
+		}
+	}
+}
+
+impl MetricValues for LocalMetrics
+{
+	const METRICS: &'static [Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Idle, Metric::Activity];
+
+	fn value(&self, metric: Metric) -> Option<f32>
+	{
+		match metric
+		{
+			Metric::Occupancy => Some(self.occupancy.occupancy),
+			Metric::Stall => Some(self.stall.stall),
+			Metric::Idle => Some(self.stall.idle),
+			Metric::Activity => Some(self.stall.activity),
+			_ => None,
+		}
+	}
+}
+
+impl MetricValues for ChannelMetrics
+{
+	const METRICS: &'static [Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Idle, Metric::Activity,
+		Metric::Bandwidth, Metric::AverageChannelDepth, Metric::MaximumChannelDepth];
+
+	fn value(&self, metric: Metric) -> Option<f32>
+	{
+		match metric
+		{
+			Metric::Occupancy => Some(self.occupancy.occupancy),
+			Metric::Stall => Some(self.stall.stall),
+			Metric::Idle => Some(self.stall.idle),
+			Metric::Activity => Some(self.stall.activity),
+			Metric::Bandwidth => Some(self.bandwidth.bandwidth),
+			Metric::AverageChannelDepth => Some(self.channel_depth.average_channel_depth),
+			Metric::MaximumChannelDepth => Some(self.channel_depth.maximum_channel_depth as f32),
+			_ => None,
+		}
+	}
+}
+
+impl MetricValues for LoopMetrics
+{
+	const METRICS: &'static [Metric] = &[Metric::Occupancy];
+
+	fn value(&self, metric: Metric) -> Option<f32>
+	{
+		match metric
+		{
+			Metric::Occupancy => Some(self.occupancy.occupancy),
+			_ => None,
+		}
+	}
+}
+
+impl std::fmt::Display for OccupancyMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "Occupancy: {:.2} %", self.occupancy)
+	}
+}
+
+impl std::fmt::Display for StallMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "Stall: {:.2} %\nIdle: {:.2} %\nAcitivity: {:.2} %",
+			self.stall, self.idle, self.activity)
+	}
+}
+
+impl std::fmt::Display for BandwidthMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "Bandwidth: {:.2} MB/s", self.bandwidth)
+	}
+}
+
+impl std::fmt::Display for EffectivenessMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "Efficiency: {:.2} %\nBurst size: {:.2}", self.efficiency, self.burst_size)?;
+
+		if let Some(cache_hit) = self.cache_hit
+		{
+			write!(formatter, "\nCache hit: {:.2} %", cache_hit)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl std::fmt::Display for ChannelDepthMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "Channel Depth: {:.2} (maximum: {})",
+			self.average_channel_depth, self.maximum_channel_depth)
+	}
+}
+
+impl std::fmt::Display for GlobalMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}\n{}\n{}\n{}",
+			self.occupancy, self.stall, self.bandwidth, self.effectiveness)
+	}
+}
+
+impl std::fmt::Display for LocalMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}\n{}", self.occupancy, self.stall)
+	}
+}
+
+impl std::fmt::Display for ChannelMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}\n{}\n{}\n{}",
+			self.occupancy, self.stall, self.bandwidth, self.channel_depth)
+	}
+}
+
+impl std::fmt::Display for LoopMetrics
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}", self.occupancy)
+	}
+}
+
+pub fn compute_global(samples: &[(&Kernel, &Global)]) -> GlobalMetrics
+{
+	GlobalMetrics
+	{
+		occupancy: compute_occupancy(samples.iter()),
+		stall: compute_stall(samples.iter()),
+		bandwidth: compute_bandwith(samples.iter()),
+		effectiveness: compute_effectiveness(samples.iter()),
+	}
+}
+
+pub fn compute_local(samples: &[(&Kernel, &Local)]) -> LocalMetrics
+{
+	LocalMetrics
+	{
+		occupancy: compute_occupancy(samples.iter()),
+		stall: compute_stall(samples.iter()),
+	}
+}
+
+pub fn compute_channel(samples: &[(&Kernel, &Channel)]) -> ChannelMetrics
+{
+	ChannelMetrics
+	{
+		occupancy: compute_occupancy(samples.iter()),
+		stall: compute_stall(samples.iter()),
+		bandwidth: compute_bandwith(samples.iter()),
+		channel_depth: compute_channel_depth(samples.iter()),
+	}
+}
+
+pub fn compute_loop(samples: &[(&Kernel, &Loop)]) -> LoopMetrics
+{
+	LoopMetrics
+	{
+		occupancy: compute_occupancy(samples.iter()),
+	}
+}
+
+pub fn compute_occupancy<'a, Samples, Sample>(samples: Samples) -> OccupancyMetrics
+where
+	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
+	Sample: Occupancy + Folded + 'a,
+{
+	let (occupancy_sum, cycles_sum) = samples
+		.map(|(kernel, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.occupancy)
+				.unwrap_or_else(|| occupancy_sums(kernel, *sample)))
+		.fold((0u64, 0u64), |(occupancy_sum, cycles_sum), (occupancy, cycles)|
+			(occupancy_sum + occupancy, cycles_sum + cycles));
+
+	OccupancyMetrics
+	{
+		occupancy: occupancy_sum as f32 / cycles_sum as f32 * 100.,
+	}
+}
+
+pub fn compute_stall<'a, Samples, Sample>(samples: Samples) -> StallMetrics
+where
+	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
+	Sample: Stall + Folded + 'a,
+{
+	let (stall_sum, idle_sum, acitvity_sum, cycles_sum) = samples
+		.map(|(kernel, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.stall)
+				.unwrap_or_else(|| stall_sums(kernel, *sample)))
+		.fold((0u64, 0u64, 0u64, 0u64),
+			|(stall_sum, idle_sum, activity_sum, cycles_sum), (stall, idle, activity, cycles)|
+				(stall_sum + stall,
+					idle_sum + idle,
+					activity_sum + activity,
+					cycles_sum + cycles));
+
+	StallMetrics
+	{
+		stall: stall_sum as f32 / cycles_sum as f32 * 100.,
+		idle: idle_sum as f32 / cycles_sum as f32 * 100.,
+		activity: acitvity_sum as f32 / cycles_sum as f32 * 100.,
+	}
+}
+
+pub fn compute_bandwith<'a, Samples, Sample>(samples: Samples) -> BandwidthMetrics
+where
+	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)> + Clone,
+	Sample: Bandwidth + Folded + 'a,
+{
+	let bandwidth_sum = samples.clone()
+		.map(|(kernel, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.bandwidth)
+				.unwrap_or_else(|| bandwidth_sum(kernel, *sample)))
+		.sum::<f32>();
+
+	let total_runtime = samples
+		.map(|(kernel, _)| (kernel.start_time, kernel.end_time))
+		.collect::<std::collections::BTreeSet<_>>()
+		.into_iter()
+		.fold(Vec::<(u64, u64)>::new(), |mut stack, (start_time, end_time)|
+			{
+				let extend = stack.last_mut()
+					.filter(|(_, latest_end_time)| start_time <= *latest_end_time);
+
+				if let Some((_, latest_end_time)) = extend
+				{
+					*latest_end_time = end_time;
+				}
+				else
+				{
+					stack.push((start_time, end_time));
+				}
+
+				stack
+			})
+		.into_iter()
+		.fold(0, |sum, (start_time, end_time)| sum + (end_time - start_time));
+
+	BandwidthMetrics
+	{
+		bandwidth: bandwidth_sum / total_runtime as f32,
+	}
+}
+
+pub fn compute_effectiveness<'a, Samples, Sample>(samples: Samples) -> EffectivenessMetrics
+where
+	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)> + Clone,
+	Sample: Effectiveness + Occupancy + Folded + 'a,
+{
+	let (number_of_samples, bandwidth_effective_sum, average_burst_size_sum) = samples.clone()
+		.map(|(_, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.effectiveness)
+				.unwrap_or_else(|| effectiveness_sums(*sample)))
+		.fold((0, 0., 0.),
+			|(number_of_samples, bandwidth_effective_sum, average_burst_size_sum),
+				(samples, bandwidth_effective, average_burst_size)|
+					(number_of_samples + samples,
+						bandwidth_effective_sum + bandwidth_effective,
+						average_burst_size_sum + average_burst_size));
+
+	let (cache_hit_samples_present, cache_hit_sum, occupancy_sum) = samples
+		.map(|(_, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.cache_hit)
+				.unwrap_or_else(|| cache_hit_sums(*sample)))
+		.fold((false, 0u64, 0u64),
+			|(cache_hit_samples_present, cache_hit_sum, occupancy_sum), (present, cache_hit, occupancy)|
+				(cache_hit_samples_present || present, cache_hit_sum + cache_hit, occupancy_sum + occupancy));
+
+	EffectivenessMetrics
+	{
+		efficiency: bandwidth_effective_sum / number_of_samples as f32 * 100.,
+		burst_size: average_burst_size_sum / number_of_samples as f32,
+		cache_hit: cache_hit_samples_present
+			.then(|| cache_hit_sum as f32 / occupancy_sum as f32 * 100.),
+	}
+}
+
+pub fn compute_channel_depth<'a, Samples, Sample>(samples: Samples) -> ChannelDepthMetrics
+where
+	Samples: Iterator<Item = &'a (&'a Kernel, &'a Sample)>,
+	Sample: ChannelDepth + Folded + 'a,
+{
+	let (number_of_samples, average_channel_depth_sum, maximum_channel_depth_overall) = samples
+		.map(|(_, sample)|
+			sample.sample_sums()
+				.map(|sums| sums.channel_depth)
+				.unwrap_or_else(|| channel_depth_sums(*sample)))
+		.fold((0, 0., 0),
+			|(number_of_samples, average_channel_depth_sum, maximum_channel_depth_overall),
+				(samples, average_channel_depth, maximum_channel_depth)|
+					(number_of_samples + samples,
+						average_channel_depth_sum + average_channel_depth,
+						maximum_channel_depth_overall.max(maximum_channel_depth)));
+
+	ChannelDepthMetrics
+	{
+		average_channel_depth: average_channel_depth_sum / number_of_samples as f32,
+		maximum_channel_depth: maximum_channel_depth_overall,
+	}
+}
+
+/// Replaces the samples of the module instances of a kernel invocation by their sums, which suffice
+/// for the metrics, whereas the timeline, the sparklines, the charts and the validation need the
+/// samples themselves
+pub fn fold_samples(kernel: &mut Kernel)
+{
+	let mut children = std::mem::take(&mut kernel.children);
+
+	for child in children.iter_mut()
+	{
+		let module_instance_details = match child
+		{
+			Child::ModuleInstance(module_instance) => &mut module_instance.module_instance_details,
+			_ => continue,
+		};
+
+		match module_instance_details
+		{
+			ModuleInstanceDetails::Global(sample) =>
+			{
+				sample.sample_sums = Some(Box::new(SampleSums
+				{
+					occupancy: occupancy_sums(kernel, sample),
+					stall: stall_sums(kernel, sample),
+					bandwidth: bandwidth_sum(kernel, sample),
+					effectiveness: effectiveness_sums(sample),
+					cache_hit: cache_hit_sums(sample),
+					..Default::default()
+				}));
+
+				sample.occupancy_samples = Vec::new();
+				sample.stall_samples = Vec::new();
+				sample.idle_samples = Vec::new();
+				sample.activity_samples = Vec::new();
+				sample.bandwidth_samples = Vec::new();
+				sample.bandwidth_effective_samples = Vec::new();
+				sample.cache_hit_samples = Vec::new();
+				sample.average_burst_size = Vec::new();
+			}
+			ModuleInstanceDetails::Local(sample) =>
+			{
+				sample.sample_sums = Some(Box::new(SampleSums
+				{
+					occupancy: occupancy_sums(kernel, sample),
+					stall: stall_sums(kernel, sample),
+					..Default::default()
+				}));
+
+				sample.occupancy_samples = Vec::new();
+				sample.stall_samples = Vec::new();
+				sample.idle_samples = Vec::new();
+				sample.activity_samples = Vec::new();
+			}
+			ModuleInstanceDetails::Channel(sample) =>
+			{
+				sample.sample_sums = Some(Box::new(SampleSums
+				{
+					occupancy: occupancy_sums(kernel, sample),
+					stall: stall_sums(kernel, sample),
+					bandwidth: bandwidth_sum(kernel, sample),
+					channel_depth: channel_depth_sums(sample),
+					..Default::default()
+				}));
+
+				sample.occupancy_samples = Vec::new();
+				sample.stall_samples = Vec::new();
+				sample.idle_samples = Vec::new();
+				sample.activity_samples = Vec::new();
+				sample.bandwidth_samples = Vec::new();
+				sample.average_channel_depth_samples = Vec::new();
+				sample.maximum_channel_depth_samples = Vec::new();
+			}
+			ModuleInstanceDetails::Loop(sample) =>
+			{
+				sample.sample_sums = Some(Box::new(SampleSums
+				{
+					occupancy: occupancy_sums(kernel, sample),
+					..Default::default()
+				}));
+
+				sample.occupancy_samples = Vec::new();
+			}
+			ModuleInstanceDetails::Unknown(_) => (),
+		}
+	}
+
+	kernel.children = children;
+}
+
+fn occupancy_sums<Sample: Occupancy>(kernel: &Kernel, sample: &Sample) -> (u64, u64)
+{
+	sample.occupancy_samples().iter()
+		.zip(kernel.total_cycles_between_samples.iter().flatten())
+		.fold((0, 0), |(occupancy_sum, cycles_sum), (&occupancy, &cycles)|
+			(occupancy_sum + occupancy, cycles_sum + cycles))
+}
+
+fn stall_sums<Sample: Stall>(kernel: &Kernel, sample: &Sample) -> (u64, u64, u64, u64)
+{
+	sample.stall_samples().iter()
+		.zip(sample.idle_samples().iter())
+		.zip(sample.activity_samples().iter())
+		.zip(kernel.total_cycles_between_samples.iter().flatten())
+		.fold((0, 0, 0, 0),
+			|(stall_sum, idle_sum, activity_sum, cycles_sum), (((&stall, &idle), &activity), &cycles)|
+				(stall_sum + stall,
+					idle_sum + idle,
+					activity_sum + activity,
+					cycles_sum + cycles))
+}
+
+fn bandwidth_sum<Sample: Bandwidth>(kernel: &Kernel, sample: &Sample) -> f32
+{
+	let intervals = std::iter::once(&kernel.start_time)
+		.chain(kernel.sample_timestamps.iter())
+		.zip(kernel.sample_timestamps.iter());
+
+	intervals.zip(sample.bandwidth_samples().iter())
+		.map(|((start_time, end_time), bandwidth)|
+			{
+				(end_time - start_time) as f32 * bandwidth
+			})
+		.sum::<f32>()
+}
+
+fn effectiveness_sums<Sample: Effectiveness>(sample: &Sample) -> (u32, f32, f32)
+{
+	sample.bandwidth_effective_samples().iter()
+		.zip(sample.average_burst_size().iter())
+		.fold((0, 0., 0.),
+			|(number_of_samples, bandwidth_effective_sum, average_burst_size_sum),
+				(&bandwidth_effective, &average_burst_size)|
+					(number_of_samples + 1,
+						bandwidth_effective_sum + bandwidth_effective,
+						average_burst_size_sum + average_burst_size))
+}
+
+fn cache_hit_sums<Sample: Effectiveness + Occupancy>(sample: &Sample) -> (bool, u64, u64)
+{
+	sample.cache_hit_samples().iter()
+		.zip(sample.occupancy_samples().iter())
+		.fold((false, 0, 0), |(_, cache_hit_sum, occupancy_sum), (&cache_hit, &occupancy)|
+			(true, cache_hit_sum + cache_hit, occupancy_sum + occupancy))
+}
+
+fn channel_depth_sums<Sample: ChannelDepth>(sample: &Sample) -> (u32, f32, u32)
+{
+	sample.average_channel_depth_samples().iter()
+		.zip(sample.maximum_channel_depth_samples().iter())
+		.fold((0, 0., 0),
+			|(number_of_samples, average_channel_depth_sum, maximum_channel_depth_overall),
+				(&average_channel_depth, &maximum_channel_depth)|
+					(number_of_samples + 1,
+						average_channel_depth_sum + average_channel_depth,
+						maximum_channel_depth_overall.max(maximum_channel_depth)))
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures::*;
+	use crate::report::*;
+
+	fn kernel(start_time: u64, samples: [u64; 3]) -> serde_json::Value
+	{
+		let children = ["__global", "__local", "__channel", "__loop"].iter()
+			.map(|mem_type| module_instance(mem_type, "kernel.cpp", 1, details(mem_type, &samples)))
+			.collect();
+
+		let sample_timestamps = [1000, 3000, 4000].map(|time| start_time + time);
+
+		crate::fixtures::kernel("kernel", start_time, &sample_timestamps, children)
+	}
+
+	#[test]
+	fn folded_samples_give_the_same_metrics()
+	{
+		let profile = profile(vec![kernel(0, [100, 700, 300]), kernel(5000, [900, 0, 1000])]);
+
+		let samples = load(profile.clone());
+		let folded = serde_json::from_value::<FoldedProfile>(profile).unwrap().0;
+
+		match &folded.kernels.nodes[0]
+		{
+			Node::Kernel(kernel) => match &kernel.children[0]
+			{
+				Child::ModuleInstance(ModuleInstance
+				{
+					module_instance_details: ModuleInstanceDetails::Global(global), ..
+				}) =>
+				{
+					assert!(global.occupancy_samples.is_empty());
+					assert_eq!(global.sample_sums.as_ref().unwrap().occupancy, (1100, 4000));
+				}
+				child => panic!("unexpected child {:?}", child),
+			},
+			node => panic!("unexpected node {:?}", node),
+		}
+
+		let settings = Settings { expand: true, ..Default::default() };
+
+		assert_eq!(serde_json::to_value(Report::new(&folded, &settings)).unwrap(),
+			serde_json::to_value(Report::new(&samples, &settings)).unwrap());
+	}
+}
@@ -3,7 +3,7 @@ use crate::data_model::*;
 pub trait Interaction
 {
 	fn operation_type(&self) -> &OperationType;
-	fn coalesced_memory(&self) -> &bool;
+	fn coalesced_memory(&self) -> Option<bool>;
 }
 
 macro_rules! impl_interaction
@@ -17,14 +17,42 @@ macro_rules! impl_interaction
 				&self.operation_type
 			}
 
-			fn coalesced_memory(&self) -> &bool
+			fn coalesced_memory(&self) -> Option<bool>
 			{
-				&self.coalesced_memory
+				self.coalesced_memory
 			}
 		}
 	};
 }
 
+pub trait Folded
+{
+	/// Sums of the samples if these were folded while loading
+	fn sample_sums(&self) -> Option<&SampleSums>;
+}
+
+macro_rules! impl_folded
+{
+	($struct_name:ident) =>
+	{
+		impl Folded for $struct_name
+		{
+			fn sample_sums(&self) -> Option<&SampleSums>
+			{
+				self.sample_sums.as_deref()
+			}
+		}
+	};
+}
+
+/// Samples of a module instance, which must not have been folded while loading
+fn unfolded<'a, Samples: ?Sized>(sample_sums: &Option<Box<SampleSums>>, samples: &'a Samples) -> &'a Samples
+{
+	assert!(sample_sums.is_none(), "samples requested, but folded while loading");
+
+	samples
+}
+
 pub trait Occupancy
 {
 	fn occupancy_samples(&self) -> &[u64];
@@ -38,7 +66,7 @@ macro_rules! impl_occupancy
 		{
 			fn occupancy_samples(&self) -> &[u64]
 			{
-				&self.occupancy_samples
+				unfolded(&self.sample_sums, &self.occupancy_samples)
 			}
 		}
 	};
@@ -59,17 +87,17 @@ macro_rules! impl_stall
 		{
 			fn stall_samples(&self) -> &[u64]
 			{
-				&self.stall_samples
+				unfolded(&self.sample_sums, &self.stall_samples)
 			}
 
 			fn idle_samples(&self) -> &[u64]
 			{
-				&self.idle_samples
+				unfolded(&self.sample_sums, &self.idle_samples)
 			}
 
 			fn activity_samples(&self) -> &[u64]
 			{
-				&self.activity_samples
+				unfolded(&self.sample_sums, &self.activity_samples)
 			}
 		}
 	};
@@ -88,7 +116,7 @@ macro_rules! impl_bandwidth
 		{
 			fn bandwidth_samples(&self) -> &[f32]
 			{
-				&self.bandwidth_samples
+				unfolded(&self.sample_sums, &self.bandwidth_samples)
 			}
 		}
 	};
@@ -109,17 +137,17 @@ macro_rules! impl_effectivness
 		{
 			fn bandwidth_effective_samples(&self) -> &[f32]
 			{
-				&self.bandwidth_effective_samples
+				unfolded(&self.sample_sums, &self.bandwidth_effective_samples)
 			}
 
 			fn cache_hit_samples(&self) -> &[u64]
 			{
-				&self.cache_hit_samples
+				unfolded(&self.sample_sums, &self.cache_hit_samples)
 			}
 
 			fn average_burst_size(&self) -> &[f32]
 			{
-				&self.average_burst_size
+				unfolded(&self.sample_sums, &self.average_burst_size)
 			}
 		}
 	};
@@ -139,31 +167,35 @@ macro_rules! impl_channel_depth
 		{
 			fn average_channel_depth_samples(&self) -> &[f32]
 			{
-				&self.average_channel_depth_samples
+				unfolded(&self.sample_sums, &self.average_channel_depth_samples)
 			}
 
 			fn maximum_channel_depth_samples(&self) -> &[u32]
 			{
-				&self.maximum_channel_depth_samples
+				unfolded(&self.sample_sums, &self.maximum_channel_depth_samples)
 			}
 		}
 	};
 }
 
+impl_folded!(Global);
 impl_interaction!(Global);
 impl_occupancy!(Global);
 impl_stall!(Global);
 impl_bandwidth!(Global);
 impl_effectivness!(Global);
 
+impl_folded!(Local);
 impl_interaction!(Local);
 impl_occupancy!(Local);
 impl_stall!(Local);
 
+impl_folded!(Channel);
 impl_interaction!(Channel);
 impl_occupancy!(Channel);
 impl_stall!(Channel);
 impl_bandwidth!(Channel);
 impl_channel_depth!(Channel);
 
+impl_folded!(Loop);
 impl_occupancy!(Loop);
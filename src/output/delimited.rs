@@ -0,0 +1,258 @@
+use crate::data_model::FileReference;
+use crate::metrics::*;
+use crate::report::*;
+use crate::timeline::ModuleInstanceTimeline;
+
+/// Writes one row per module instance group, or per kernel and unroll instance if expanded, followed
+/// by a row per run and their mean and standard deviation if broken down per run
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, report: &Report, delimiter: u8)
+	-> anyhow::Result<()>
+{
+	let mut writer = csv::WriterBuilder::new()
+		.delimiter(delimiter)
+		.from_writer(writer);
+
+	writer.write_record(["memory_type", "source_file", "line", "column", "callsite", "kernel",
+			"instance", "run"].iter().copied()
+		.chain(Metric::VARIANTS.iter().copied()))?;
+
+	if report.includes(Section::GlobalMemory)
+	{
+		write_module_instances(&mut writer, "global", &report.global_memory)?;
+	}
+	if report.includes(Section::LocalMemory)
+	{
+		write_module_instances(&mut writer, "local", &report.local_memory)?;
+	}
+	if report.includes(Section::Channel)
+	{
+		write_module_instances(&mut writer, "channel", &report.channel)?;
+	}
+	if report.includes(Section::Loop)
+	{
+		write_module_instances(&mut writer, "loop", &report.loops)?;
+	}
+
+	writer.flush()?;
+
+	Ok(())
+}
+
+fn write_module_instances<Writer, Metrics>(writer: &mut csv::Writer<Writer>, memory_type: &str,
+	groups: &[ModuleInstanceGroup<Metrics>]) -> anyhow::Result<()>
+where
+	Writer: std::io::Write,
+	Metrics: MetricValues,
+{
+	for group in groups.iter()
+	{
+		// Multiple file references for one group are rare, they share the location fields
+		let join = |field: &dyn Fn(&FileReference) -> String|
+			group.source_files.iter().map(field).collect::<Vec<_>>().join(" | ");
+
+		let location = [
+			memory_type.to_owned(),
+			join(&|file_reference| file_reference.file_name.display().to_string()),
+			join(&|file_reference| file_reference.line.to_string()),
+			join(&|file_reference| file_reference.column_number
+				.map(|column| column.to_string()).unwrap_or_default()),
+			join(&|file_reference| format_callsite(&file_reference.callsite)),
+		];
+
+		let write_row = |writer: &mut csv::Writer<Writer>, kernel: &str, instance: String, run: String,
+			value: &dyn Fn(Metric) -> Option<f32>|
+		{
+			writer.write_record(location.iter().cloned()
+				.chain([kernel.to_owned(), instance, run].iter().cloned())
+				.chain(Metric::ALL.iter()
+					.map(|&metric| value(metric).map(|value| value.to_string()).unwrap_or_default())))
+		};
+
+		if let Some(kernels) = &group.kernels
+		{
+			for kernel in kernels.iter()
+			{
+				for (id, metrics) in kernel.instances.iter().enumerate()
+				{
+					write_row(writer, &kernel.name, (id + 1).to_string(), String::new(),
+						&|metric| metrics.value(metric))?;
+				}
+			}
+		}
+		else
+		{
+			write_row(writer, "", String::new(), String::new(), &|metric| group.metrics.value(metric))?;
+		}
+
+		if let Some(runs) = &group.runs
+		{
+			for (id, metrics) in runs.metrics.iter().enumerate()
+			{
+				if let Some(metrics) = metrics
+				{
+					write_row(writer, "", String::new(), (id + 1).to_string(),
+						&|metric| metrics.value(metric))?;
+				}
+			}
+
+			let statistics = |metric: Metric|
+				runs.statistics.iter().find(|statistics| statistics.metric == metric);
+
+			write_row(writer, "", String::new(), "mean".to_owned(),
+				&|metric| statistics(metric).map(|statistics| statistics.mean))?;
+			write_row(writer, "", String::new(), "standard_deviation".to_owned(),
+				&|metric| statistics(metric).map(|statistics| statistics.standard_deviation))?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Writes one row per sampling interval of every module instance timeline
+pub fn write_timelines<Writer: std::io::Write>(writer: &mut Writer, timelines: &[ModuleInstanceTimeline],
+	delimiter: u8) -> anyhow::Result<()>
+{
+	let mut writer = csv::WriterBuilder::new()
+		.delimiter(delimiter)
+		.from_writer(writer);
+
+	writer.write_record(["memory_type", "source_file", "line", "column", "callsite", "kernel",
+			"module_instance", "interval", "start_time", "end_time", "cycles"].iter().copied()
+		.chain(Metric::VARIANTS.iter().copied()))?;
+
+	for timeline in timelines.iter()
+	{
+		let join = |field: &dyn Fn(&FileReference) -> String|
+			timeline.source_files.iter().map(field).collect::<Vec<_>>().join(" | ");
+
+		let location = [
+			timeline.memory_type.to_owned(),
+			join(&|file_reference| file_reference.file_name.display().to_string()),
+			join(&|file_reference| file_reference.line.to_string()),
+			join(&|file_reference| file_reference.column_number
+				.map(|column| column.to_string()).unwrap_or_default()),
+			join(&|file_reference| format_callsite(&file_reference.callsite)),
+			timeline.kernel.clone(),
+			timeline.module_instance.clone(),
+		];
+
+		for (id, interval) in timeline.intervals.iter().enumerate()
+		{
+			writer.write_record(location.iter().cloned()
+				.chain([
+					(id + 1).to_string(),
+					interval.start_time.to_string(),
+					interval.end_time.to_string(),
+					interval.cycles.map(|cycles| cycles.to_string()).unwrap_or_default(),
+				].iter().cloned())
+				.chain(Metric::ALL.iter()
+					.map(|metric|
+						timeline.metrics.iter().position(|other| other == metric)
+							.map(|index| interval.values[index].to_string())
+							.unwrap_or_default())))?;
+		}
+	}
+
+	writer.flush()?;
+
+	Ok(())
+}
+
+/// Flattens the callsite chain into a single field, e.g. `helper.hpp:7 < main.cpp:42:3`
+fn format_callsite(callsite: &[FileReference]) -> String
+{
+	callsite.iter()
+		.map(|file_reference|
+			{
+				std::iter::once(file_reference.to_string())
+					.chain((!file_reference.callsite.is_empty())
+						.then(|| format_callsite(&file_reference.callsite)))
+					.collect::<Vec<_>>().join(" < ")
+			})
+		.collect::<Vec<_>>().join(" | ")
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn written(settings: &Settings, delimiter: u8) -> String
+	{
+		let report = Report::new(&fixtures::load(fixtures::example()), settings);
+
+		let mut output = Vec::new();
+		write(&mut output, &report, delimiter).unwrap();
+
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn write_lists_a_row_per_module_instance_group()
+	{
+		assert_eq!(written(&Settings::default(), b','), "\
+			memory_type,source_file,line,column,callsite,kernel,instance,run,occupancy,stall,idle,activity,\
+				bandwidth,efficiency,burst_size,cache_hit,average_channel_depth,maximum_channel_depth\n\
+			global,/src/kernel.cpp,10,,main.cpp:3,,,,75,37.5,18.75,75,750,75,750,50,,\n\
+			loop,/src/kernel.cpp,20,,,,,,50,,,,,,,,,\n");
+	}
+
+	#[test]
+	fn write_expanded_lists_a_row_per_unroll_instance()
+	{
+		let settings = Settings
+		{
+			expand: true,
+			sections: Some(vec![Section::Loop]),
+			..Default::default()
+		};
+
+		assert_eq!(written(&settings, b'\t').lines().skip(1).collect::<Vec<_>>(), vec![
+			"loop\t/src/kernel.cpp\t20\t\t\tK\t1\t\t100\t\t\t\t\t\t\t\t\t",
+			"loop\t/src/kernel.cpp\t20\t\t\tK\t2\t\t0\t\t\t\t\t\t\t\t\t",
+		]);
+	}
+
+	#[test]
+	fn write_per_run_lists_a_row_per_run_with_their_statistics()
+	{
+		let settings = Settings
+		{
+			sections: Some(vec![Section::Loop]),
+			..Default::default()
+		};
+		let profile = fixtures::load(fixtures::example());
+		let mut report = Report::new(&profile, &settings);
+		report.add_runs(&Report::runs(&[profile.clone(), profile], &settings));
+
+		let mut output = Vec::new();
+		write(&mut output, &report, b',').unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().skip(1).collect::<Vec<_>>(), vec![
+			"loop,/src/kernel.cpp,20,,,,,,50,,,,,,,,,",
+			"loop,/src/kernel.cpp,20,,,,,1,50,,,,,,,,,",
+			"loop,/src/kernel.cpp,20,,,,,2,50,,,,,,,,,",
+			"loop,/src/kernel.cpp,20,,,,,mean,50,,,,,,,,,",
+			"loop,/src/kernel.cpp,20,,,,,standard_deviation,0,,,,,,,,,",
+		]);
+	}
+
+	#[test]
+	fn write_timelines_lists_a_row_per_interval()
+	{
+		let profile = fixtures::load(fixtures::example());
+		let timelines = crate::timeline::timelines(&profile, &Settings::default(),
+			&"kernel.cpp:20".parse().unwrap());
+
+		let mut output = Vec::new();
+		write_timelines(&mut output, &timelines[..1], b',').unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap(), "\
+			memory_type,source_file,line,column,callsite,kernel,module_instance,interval,start_time,end_time,\
+				cycles,occupancy,stall,idle,activity,bandwidth,efficiency,burst_size,cache_hit,\
+				average_channel_depth,maximum_channel_depth\n\
+			loop,/src/kernel.cpp,20,,,K,loop0,1,0,1000,1000,100,,,,,,,,,\n\
+			loop,/src/kernel.cpp,20,,,K,loop0,2,1000,2000,1000,100,,,,,,,,,\n");
+	}
+}
@@ -0,0 +1,401 @@
+use crate::data_model::*;
+use crate::metrics::Metric;
+use crate::report::*;
+use crate::series::*;
+
+use super::text::format_time;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+code { font-size: 0.9em; }
+figure { display: inline-block; margin: 0 1em 1em 0; }
+figcaption { font-size: 0.9em; margin-bottom: 0.3em; }
+svg text { font-size: 10px; fill: #555; }
+";
+
+/// Colors of the sample charts, per charted metric
+const LINES: &[(Metric, &str)] = &[
+	(Metric::Occupancy, "#1f77b4"),
+	(Metric::Stall, "#d62728"),
+	(Metric::Bandwidth, "#2ca02c"),
+];
+
+/// Sampled values of a metric as points of a step function over time
+struct Line
+{
+	metric: Metric,
+	color: &'static str,
+	points: Vec<(u64, f32)>,
+}
+
+/// Invocations charted per module instance location, the others are only counted, as profiles may
+/// contain thousands of invocations
+const MAXIMUM_CHARTS: usize = 8;
+
+const CHART_WIDTH: f64 = 480.;
+const CHART_HEIGHT: f64 = 120.;
+const MARGIN: f64 = 40.;
+
+const MARKUP: super::Markup = super::Markup
+{
+	escape,
+	code: |text| format!("<code>{}</code>", escape(text)),
+	location: format_location,
+};
+
+/// Writes a single self-contained HTML document with the report, a timeline of the kernel
+/// invocations and charts of the sampled occupancy, stall and bandwidth of the module instances of
+/// the included sections
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, profile: &Profile, report: &Report,
+	settings: &Settings) -> std::io::Result<()>
+{
+	let title = format!("{}: {} (aocx: {})",
+		report.json_type,
+		report.versions.profiler_json_version,
+		report.versions.aocx_version);
+
+	writeln!(writer, "<!DOCTYPE html>")?;
+	writeln!(writer, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+	writeln!(writer, "<title>{}</title>\n<style>{}</style>", escape(&title), STYLE)?;
+	writeln!(writer, "</head>\n<body>\n<h1>{}</h1>", escape(&title))?;
+
+	for table in super::tables(report, &MARKUP).iter()
+	{
+		write_section(writer, table.title, &table.header, &table.rows)?;
+
+		if table.section == Section::Kernels && !report.kernels.is_empty()
+		{
+			writeln!(writer, "{}", kernel_timeline(&report.kernels))?;
+		}
+	}
+
+	write_samples(writer, profile, settings)?;
+
+	writeln!(writer, "</body>\n</html>")
+}
+
+/// Cells are expected to be escaped already
+fn write_section<Writer: std::io::Write>(writer: &mut Writer, title: &str, header: &[String],
+	rows: &[Vec<String>]) -> std::io::Result<()>
+{
+	writeln!(writer, "<h2>{}</h2>", title)?;
+
+	if rows.is_empty()
+	{
+		return writeln!(writer, "<p><em>None</em></p>");
+	}
+
+	writeln!(writer, "<table>\n<tr>{}</tr>", header.iter()
+		.map(|column| format!("<th>{}</th>", escape(column)))
+		.collect::<String>())?;
+
+	for row in rows.iter()
+	{
+		writeln!(writer, "<tr>{}</tr>", row.iter()
+			.map(|cell| format!("<td>{}</td>", cell))
+			.collect::<String>())?;
+	}
+
+	writeln!(writer, "</table>")
+}
+
+/// Charts of the sampled metrics per sampling interval, for the module instances of the first
+/// kernel invocations, grouped by memory type and source location
+fn write_samples<Writer: std::io::Write>(writer: &mut Writer, profile: &Profile, settings: &Settings)
+	-> std::io::Result<()>
+{
+	let module_instances = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel))
+		.flat_map(|kernel|
+			kernel.children.iter()
+				.filter_map(|child|
+					match child
+					{
+						Child::ModuleInstance(module_instance) => Some(module_instance),
+						_ => None
+					})
+				.filter(|module_instance| settings.considers_source(&module_instance.source_files))
+				.map(move |module_instance| (kernel, module_instance)))
+		.fold(std::collections::BTreeMap::new(), |mut map, (kernel, module_instance)|
+			{
+				// Ordered as in the report
+				let (section, title) = match module_instance.module_instance_details
+				{
+					ModuleInstanceDetails::Global(_) => (Section::GlobalMemory, "Global memory"),
+					ModuleInstanceDetails::Local(_) => (Section::LocalMemory, "Local memory"),
+					ModuleInstanceDetails::Channel(_) => (Section::Channel, "Channel"),
+					ModuleInstanceDetails::Loop(_) => (Section::Loop, "Loop"),
+					ModuleInstanceDetails::Unknown(_) => return map,
+				};
+
+				if !settings.includes(section)
+				{
+					return map;
+				}
+
+				map.entry((section, title)).or_insert_with(std::collections::BTreeMap::new)
+					.entry(&module_instance.source_files).or_insert_with(Vec::new)
+						.push((kernel, module_instance));
+
+				map
+			});
+
+	if module_instances.is_empty()
+	{
+		return Ok(());
+	}
+
+	writeln!(writer, "<h2>Samples</h2>")?;
+
+	for ((_, memory_type), locations) in module_instances.into_iter()
+	{
+		writeln!(writer, "<h3>{}</h3>", memory_type)?;
+
+		for (source_files, module_instances) in locations.into_iter()
+		{
+			writeln!(writer, "<details>\n<summary>{}</summary>", format_location(source_files))?;
+
+			for &(kernel, module_instance) in module_instances.iter().take(MAXIMUM_CHARTS)
+			{
+				writeln!(writer, "<figure>\n<figcaption>Kernel <code>{}</code>, {} (start: {}, end: {})\
+					</figcaption>",
+					escape(&kernel.name), escape(&module_instance.name), kernel.start_time, kernel.end_time)?;
+				writeln!(writer, "{}", sample_charts(kernel, &module_instance.module_instance_details))?;
+				writeln!(writer, "</figure>")?;
+			}
+
+			if module_instances.len() > MAXIMUM_CHARTS
+			{
+				writeln!(writer, "<p><em>{} more invocations not charted</em></p>",
+					module_instances.len() - MAXIMUM_CHARTS)?;
+			}
+
+			writeln!(writer, "</details>")?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Percentages share one chart, the bandwidth gets its own scale
+fn sample_charts(kernel: &Kernel, module_instance_details: &ModuleInstanceDetails) -> String
+{
+	let intervals = intervals(kernel);
+
+	let lines = LINES.iter()
+		.filter_map(|&(metric, color)|
+			metric_series(kernel, module_instance_details, metric)
+				.map(|series|
+					{
+						let points = intervals.iter()
+							.zip(series)
+							.flat_map(|(&(start_time, end_time), value)|
+								vec![(start_time, value), (end_time, value)])
+							.collect::<Vec<_>>();
+
+						Line { metric, color, points }
+					}))
+		.collect::<Vec<_>>();
+
+	let (percentages, others): (Vec<_>, Vec<_>) = lines.into_iter()
+		.partition(|line| line.metric.unit() == " %");
+
+	[percentages, others].iter()
+		.filter(|lines| !lines.is_empty())
+		.map(|lines|
+			{
+				let maximum = lines.iter()
+					.flat_map(|line| line.points.iter().map(|&(_, value)| value))
+					.fold(0f32, f32::max);
+
+				// Percentages keep a fixed scale to be comparable between charts
+				let maximum = if lines[0].metric.unit() == " %" { maximum.max(100.) } else { maximum };
+
+				line_chart(lines, kernel.start_time, kernel.end_time, maximum)
+			})
+		.collect::<Vec<_>>().join("\n")
+}
+
+fn line_chart(lines: &[Line], start_time: u64, end_time: u64, maximum: f32)
+	-> String
+{
+	let duration = (end_time - start_time).max(1) as f64;
+	let maximum = if maximum > 0. { maximum as f64 } else { 1. };
+
+	let x = |time: u64| MARGIN + (time.saturating_sub(start_time)) as f64 / duration * CHART_WIDTH;
+	let y = |value: f32| MARGIN / 2. + CHART_HEIGHT - value as f64 / maximum * CHART_HEIGHT;
+
+	let polylines = lines.iter()
+		.map(|line|
+			format!("<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\">\
+				<title>{}</title></polyline>",
+				line.color,
+				line.points.iter()
+					.map(|&(time, value)| format!("{:.1},{:.1}", x(time), y(value)))
+					.collect::<Vec<_>>().join(" "),
+				line.metric.label()))
+		.collect::<String>();
+
+	let legend = lines.iter()
+		.enumerate()
+		.map(|(index, line)|
+			format!("<text x=\"{:.1}\" y=\"10\" style=\"fill: {}\">{}{}</text>",
+				MARGIN + index as f64 * 120., line.color, line.metric.label(),
+				match line.metric.unit().trim() { "" => String::new(), unit => format!(" ({})", unit) }))
+		.collect::<String>();
+
+	format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\">\
+		{legend}\
+		<line x1=\"{margin}\" y1=\"{top}\" x2=\"{margin}\" y2=\"{bottom}\" stroke=\"#999\"/>\
+		<line x1=\"{margin}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"#999\"/>\
+		<text x=\"{label}\" y=\"{top}\" text-anchor=\"end\">{maximum:.0}</text>\
+		<text x=\"{label}\" y=\"{bottom}\" text-anchor=\"end\">0</text>\
+		<text x=\"{margin}\" y=\"{time}\">{start}</text>\
+		<text x=\"{right}\" y=\"{time}\" text-anchor=\"end\">{end}</text>\
+		{polylines}</svg>",
+		width = CHART_WIDTH + 2. * MARGIN,
+		height = CHART_HEIGHT + 1.5 * MARGIN,
+		legend = legend,
+		margin = MARGIN,
+		top = MARGIN / 2.,
+		bottom = MARGIN / 2. + CHART_HEIGHT,
+		right = MARGIN + CHART_WIDTH,
+		label = MARGIN - 4.,
+		time = MARGIN / 2. + CHART_HEIGHT + 14.,
+		maximum = maximum,
+		start = format_time(0.),
+		end = format_time(duration),
+		polylines = polylines)
+}
+
+/// Gantt chart with one row per kernel and a bar per invocation
+fn kernel_timeline(kernels: &[KernelSummary]) -> String
+{
+	const ROW_HEIGHT: f64 = 20.;
+	const LABEL_WIDTH: f64 = 200.;
+	const WIDTH: f64 = 720.;
+
+	let invocations = || kernels.iter().flat_map(|kernel| kernel.invocations.iter());
+
+	let start_time = invocations().map(|invocation| invocation.start_time).min().unwrap_or(0);
+	let end_time = invocations().map(|invocation| invocation.end_time).max().unwrap_or(0);
+	let duration = (end_time - start_time).max(1) as f64;
+
+	let x = |time: u64| LABEL_WIDTH + (time - start_time) as f64 / duration * WIDTH;
+
+	let rows = kernels.iter()
+		.enumerate()
+		.map(|(index, kernel)|
+			{
+				let y = index as f64 * ROW_HEIGHT;
+
+				let bars = kernel.invocations.iter()
+					.map(|invocation|
+						format!("<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+							fill=\"{}\"><title>{} (start: {}, end: {})</title></rect>",
+							x(invocation.start_time), y + 3.,
+							(x(invocation.end_time) - x(invocation.start_time)).max(1.), ROW_HEIGHT - 6.,
+							if invocation.is_autorun { "#ff7f0e" } else { "#1f77b4" },
+							format_time(invocation.runtime as f64), invocation.start_time, invocation.end_time))
+					.collect::<String>();
+
+				format!("<text x=\"0\" y=\"{:.1}\">{}</text>{}", y + ROW_HEIGHT - 6., escape(&kernel.name), bars)
+			})
+		.collect::<String>();
+
+	let height = kernels.len() as f64 * ROW_HEIGHT;
+
+	format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">{}\
+		<text x=\"{}\" y=\"{}\">{}</text>\
+		<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text></svg>",
+		LABEL_WIDTH + WIDTH, height + 16., rows,
+		LABEL_WIDTH, height + 12., format_time(0.),
+		LABEL_WIDTH + WIDTH, height + 12., format_time(duration))
+}
+
+/// Code elements of the file references with their callsite chains
+fn format_location(file_references: &[FileReference]) -> String
+{
+	file_references.iter()
+		.map(|file_reference|
+			std::iter::once(format!("<code>{}</code>", escape(&file_reference.to_string())))
+				.chain((!file_reference.callsite.is_empty())
+					.then(|| format_location(&file_reference.callsite)))
+				.collect::<Vec<_>>().join(" ⮤ "))
+		.collect::<Vec<_>>().join("<br>")
+}
+
+fn escape(text: &str) -> String
+{
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn written(profile: &Profile, settings: &Settings) -> String
+	{
+		let mut output = Vec::new();
+		write(&mut output, profile, &Report::new(profile, settings), settings).unwrap();
+
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn write_charts_only_the_included_sections()
+	{
+		let profile = fixtures::load(fixtures::example());
+		let settings = Settings { sections: Some(vec![Section::Loop]), ..Default::default() };
+
+		let html = written(&profile, &settings);
+
+		assert!(html.contains("<h2>Loop</h2>"));
+		assert!(html.contains("<h3>Loop</h3>"));
+		assert!(!html.contains("Global memory"));
+		assert!(!html.contains("<h2>Kernels</h2>"));
+		assert_eq!(html.matches("<figure>").count(), 2);
+
+		let settings = Settings { sections: Some(vec![Section::Kernels]), ..Default::default() };
+
+		assert!(!written(&profile, &settings).contains("<h2>Samples</h2>"));
+	}
+
+	#[test]
+	fn write_caps_the_charts_per_location()
+	{
+		let kernels = (0..MAXIMUM_CHARTS as u64 + 3)
+			.map(|index|
+				fixtures::kernel("K", index * 1000, &[index * 1000 + 500], vec![
+					fixtures::module_instance("loop", "/src/kernel.cpp", 20, fixtures::details("__loop", &[250])),
+				]))
+			.collect();
+		let profile = fixtures::load(fixtures::profile(kernels));
+
+		let html = written(&profile, &Settings::default());
+
+		assert_eq!(html.matches("<figure>").count(), MAXIMUM_CHARTS);
+		assert!(html.contains("<p><em>3 more invocations not charted</em></p>"));
+		assert!(html.ends_with("</body>\n</html>\n"));
+	}
+
+	#[test]
+	fn escape_replaces_the_markup_characters()
+	{
+		assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+	}
+}
@@ -0,0 +1,181 @@
+use crate::data_model::FileReference;
+use crate::report::*;
+
+const MARKUP: super::Markup = super::Markup
+{
+	escape,
+	code: code_span,
+	location: format_location,
+};
+
+/// Writes every section as GitHub/GitLab flavored Markdown table, e.g. for merge request comments
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, report: &Report) -> std::io::Result<()>
+{
+	writeln!(writer, "**{}**: {} (aocx: {})",
+		escape(&report.json_type),
+		escape(&report.versions.profiler_json_version),
+		escape(&report.versions.aocx_version))?;
+
+	for table in super::tables(report, &MARKUP).iter()
+	{
+		write_section(writer, table.title, &table.header, &table.rows)?;
+	}
+
+	Ok(())
+}
+
+/// Cells are expected to be escaped already
+fn write_section<Writer: std::io::Write>(writer: &mut Writer, title: &str, header: &[String],
+	rows: &[Vec<String>]) -> std::io::Result<()>
+{
+	writeln!(writer)?;
+	writeln!(writer, "### {}", title)?;
+	writeln!(writer)?;
+
+	if rows.is_empty()
+	{
+		writeln!(writer, "_None_")?;
+
+		return Ok(());
+	}
+
+	writeln!(writer, "| {} |", header.join(" | "))?;
+	writeln!(writer, "|{}", " --- |".repeat(header.len()))?;
+
+	for row in rows.iter()
+	{
+		writeln!(writer, "| {} |", row.join(" | "))?;
+	}
+
+	Ok(())
+}
+
+/// Code spans of the file references with their callsite chains, e.g. `` `a.cpp:42:5` ⮤ `b.hpp:7` ``
+fn format_location(file_references: &[FileReference]) -> String
+{
+	file_references.iter()
+		.map(|file_reference|
+			std::iter::once(code_span(&file_reference.to_string()))
+				.chain((!file_reference.callsite.is_empty())
+					.then(|| format_location(&file_reference.callsite)))
+				.collect::<Vec<_>>().join(" ⮤ "))
+		.collect::<Vec<_>>().join("<br>")
+}
+
+/// Backslash escapes the characters that would otherwise be taken as markup, line breaks would end
+/// the table row
+fn escape(text: &str) -> String
+{
+	text.chars()
+		.fold(String::with_capacity(text.len()), |mut escaped, character|
+			{
+				match character
+				{
+					'\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' | '!' | '&' =>
+					{
+						escaped.push('\\');
+						escaped.push(character);
+					}
+					'\n' => escaped.push_str("<br>"),
+					'\r' => {}
+					_ => escaped.push(character),
+				}
+
+				escaped
+			})
+}
+
+/// Code span fenced by more backticks than it contains in a row; backslashes have no effect within
+/// code spans, except before pipes, which end a table cell even there
+fn code_span(text: &str) -> String
+{
+	let longest_run = text.split(|character| character != '`')
+		.map(str::len)
+		.max()
+		.unwrap_or(0);
+	let fence = "`".repeat(longest_run + 1);
+	let padding = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+
+	let text = text.replace('|', "\\|").replace(['\r', '\n'], " ");
+
+	format!("{}{}{}{}{}", fence, padding, text, padding, fence)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	#[test]
+	fn escape_keeps_markup_and_line_breaks_out_of_the_cells()
+	{
+		assert_eq!(escape("a_b*c* <br> [x](y) | `z`\r\n#1"),
+			"a\\_b\\*c\\* \\<br\\> \\[x\\](y) \\| \\`z\\`<br>\\#1");
+		assert_eq!(code_span("a|b\nc_d"), "`a\\|b c_d`");
+		assert_eq!(code_span("a``b"), "```a``b```");
+		assert_eq!(code_span("`a"), "`` `a ``");
+	}
+
+	#[test]
+	fn write_renders_a_table_per_section()
+	{
+		let settings = Settings
+		{
+			sections: Some(vec![Section::Kernels, Section::GlobalMemory, Section::Channel]),
+			..Default::default()
+		};
+		let report = Report::new(&fixtures::load(fixtures::example()), &settings);
+
+		let mut output = Vec::new();
+		write(&mut output, &report).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().collect::<Vec<_>>(), vec![
+			"**profiler**: 2021.2.0.268.1 (aocx: 21.4)",
+			"",
+			"### Kernels",
+			"",
+			"| Kernel | Invocations | Total runtime | Mean runtime | Minimum runtime | Maximum runtime |",
+			"| --- | --- | --- | --- | --- | --- |",
+			"| `K` | 1 | 0.002 ms | 0.002 ms | 0.002 ms | 0.002 ms |",
+			"",
+			"### Global memory",
+			"",
+			"| Source location | Occupancy (%) | Stall (%) | Idle (%) | Activity (%) | Bandwidth (MB/s) | \
+				Efficiency (%) | Burst size | Cache hit (%) |",
+			"| --- | --- | --- | --- | --- | --- | --- | --- | --- |",
+			"| `/src/kernel.cpp:10` ⮤ `main.cpp:3` | 75.00 | 37.50 | 18.75 | 75.00 | 750.00 | 75.00 | 750.00 | \
+				50.00 |",
+			"",
+			"### Channel",
+			"",
+			"_None_",
+		]);
+	}
+
+	#[test]
+	fn write_per_run_adds_a_row_per_run_with_their_statistics()
+	{
+		let settings = Settings
+		{
+			sections: Some(vec![Section::Loop]),
+			..Default::default()
+		};
+		let profile = fixtures::load(fixtures::example());
+		let mut report = Report::new(&profile, &settings);
+		report.add_runs(&Report::runs(&[profile.clone(), profile], &settings));
+
+		let mut output = Vec::new();
+		write(&mut output, &report).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().skip(4).collect::<Vec<_>>(), vec![
+			"| Source location | Run | Occupancy (%) |",
+			"| --- | --- | --- |",
+			"| `/src/kernel.cpp:20` |  | 50.00 |",
+			"| `/src/kernel.cpp:20` | 1 | 50.00 |",
+			"| `/src/kernel.cpp:20` | 2 | 50.00 |",
+			"| `/src/kernel.cpp:20` | mean | 50.00 |",
+			"| `/src/kernel.cpp:20` | standard deviation | 0.00 |",
+		]);
+	}
+}
@@ -0,0 +1,404 @@
+mod delimited;
+mod html;
+mod markdown;
+mod text;
+
+use crate::data_model::{FileReference, Profile};
+use crate::diff::Diff;
+use crate::metrics::*;
+use crate::report::*;
+use crate::timeline::ModuleInstanceTimeline;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat
+{
+	Text,
+	Json,
+	Csv,
+	Tsv,
+	Markdown,
+	Html,
+}
+
+impl OutputFormat
+{
+	pub const VARIANTS: &'static [&'static str] = &["text", "json", "csv", "tsv", "markdown", "html"];
+}
+
+impl std::str::FromStr for OutputFormat
+{
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		match string
+		{
+			"text" => Ok(OutputFormat::Text),
+			"json" => Ok(OutputFormat::Json),
+			"csv" => Ok(OutputFormat::Csv),
+			"tsv" => Ok(OutputFormat::Tsv),
+			"markdown" => Ok(OutputFormat::Markdown),
+			"html" => Ok(OutputFormat::Html),
+			_ => Err(format!("unknown output format: {}", string)),
+		}
+	}
+}
+
+/// The HTML report charts the samples, hence needs the profile besides the report
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, profile: &Profile, report: &Report,
+	settings: &Settings, format: OutputFormat) -> anyhow::Result<()>
+{
+	match format
+	{
+		OutputFormat::Text => text::write(writer, report)?,
+		OutputFormat::Json =>
+		{
+			// Omits the excluded sections instead of listing them empty
+			let mut value = serde_json::to_value(report)?;
+
+			if let serde_json::Value::Object(object) = &mut value
+			{
+				object.retain(|key, _|
+					Section::ALL.iter()
+						.find(|section| section.key() == key)
+						.map(|&section| report.includes(section))
+						.unwrap_or(true));
+			}
+
+			serde_json::to_writer_pretty(&mut *writer, &value)?;
+			writeln!(writer)?;
+		}
+		OutputFormat::Csv => delimited::write(writer, report, b',')?,
+		OutputFormat::Tsv => delimited::write(writer, report, b'\t')?,
+		OutputFormat::Markdown => markdown::write(writer, report)?,
+		OutputFormat::Html => html::write(writer, profile, report, settings)?,
+	}
+
+	Ok(())
+}
+
+pub fn write_timelines<Writer: std::io::Write>(writer: &mut Writer, timelines: &[ModuleInstanceTimeline],
+	format: OutputFormat) -> anyhow::Result<()>
+{
+	match format
+	{
+		OutputFormat::Text => text::write_timelines(writer, timelines)?,
+		OutputFormat::Json =>
+		{
+			serde_json::to_writer_pretty(&mut *writer, timelines)?;
+			writeln!(writer)?;
+		}
+		OutputFormat::Csv => delimited::write_timelines(writer, timelines, b',')?,
+		OutputFormat::Tsv => delimited::write_timelines(writer, timelines, b'\t')?,
+		OutputFormat::Markdown => anyhow::bail!("the timeline is not available as markdown"),
+		OutputFormat::Html => anyhow::bail!("the timeline is not available as HTML"),
+	}
+
+	Ok(())
+}
+
+pub fn write_diff<Writer: std::io::Write>(writer: &mut Writer, diff: &Diff) -> anyhow::Result<()>
+{
+	text::write_diff(writer, diff)?;
+
+	Ok(())
+}
+
+/// Markup of the table cells, which differs between the formats rendering tables
+struct Markup
+{
+	/// Escapes plain text
+	escape: fn(&str) -> String,
+	/// Code span of plain text, e.g. a kernel name
+	code: fn(&str) -> String,
+	/// Source locations with their callsite chains
+	location: fn(&[FileReference]) -> String,
+}
+
+/// Section of the report as table, with the cells already in markup
+struct Table
+{
+	section: Section,
+	title: &'static str,
+	header: Vec<String>,
+	rows: Vec<Vec<String>>,
+}
+
+impl Table
+{
+	fn new(section: Section, title: &'static str, header: &[&str], rows: Vec<Vec<String>>) -> Table
+	{
+		Table
+		{
+			section,
+			title,
+			header: header.iter().map(|&column| column.to_owned()).collect(),
+			rows,
+		}
+	}
+}
+
+/// Tables of the sections included in the report, in the order of the text output; the insights
+/// are omitted if there are none
+fn tables(report: &Report, markup: &Markup) -> Vec<Table>
+{
+	let mut tables = Vec::new();
+
+	if report.includes(Section::Boards)
+	{
+		let rows = report.boards.iter()
+			.flat_map(|board|
+				board.global_memories.iter()
+					.map(move |global_memory| vec![
+						(markup.escape)(&board.board_type),
+						(markup.escape)(&global_memory.name),
+						global_memory.maximum_bandwidth.to_string(),
+						global_memory.maximum_burst.to_string(),
+					]))
+			.collect();
+
+		tables.push(Table::new(Section::Boards, "Boards",
+			&["Board", "Global memory", "Maximum theoretical bandwidth (MB/s)", "Maximum burst"], rows));
+	}
+	if report.includes(Section::RunInformation)
+	{
+		let rows = report.run_information.iter()
+			.map(|run_information| vec![run_information.fmax.to_string()])
+			.collect();
+
+		tables.push(Table::new(Section::RunInformation, "Run information", &["Fmax (MHz)"], rows));
+	}
+	if report.includes(Section::Kernels)
+	{
+		let rows = report.kernels.iter()
+			.map(|kernel| vec![
+				(markup.code)(&kernel.name),
+				kernel.invocations.len().to_string(),
+				text::format_time(kernel.total_runtime as f64),
+				text::format_time(kernel.mean_runtime),
+				text::format_time(kernel.minimum_runtime as f64),
+				text::format_time(kernel.maximum_runtime as f64),
+			])
+			.collect();
+
+		tables.push(Table::new(Section::Kernels, "Kernels",
+			&["Kernel", "Invocations", "Total runtime", "Mean runtime", "Minimum runtime", "Maximum runtime"],
+			rows));
+	}
+	if report.includes(Section::MemoryTransfers)
+	{
+		let rows = report.memory_transfers.groups.iter()
+			.map(|group| vec![
+				(markup.escape)(&group.type_transfer),
+				group.device_id.to_string(),
+				group.command_queue_id.to_string(),
+				group.count.to_string(),
+				text::format_time(group.total_time as f64),
+				format!("{:.2}", group.share),
+			])
+			.collect();
+
+		tables.push(Table::new(Section::MemoryTransfers, "Memory transfers",
+			&["Type", "Device", "Command queue", "Count", "Total time", "Share of the timeline (%)"], rows));
+	}
+	if report.includes(Section::ExternalMemory)
+	{
+		let rows = report.external_memory.iter()
+			.flat_map(|external_memory|
+				external_memory.ports.iter()
+					.map(move |port| vec![
+						(markup.escape)(&external_memory.name),
+						(markup.escape)(&port.port),
+						format!("{:.2}", port.bandwidth),
+						format!("{:.2}", port.write_burst),
+						format!("{:.2}", port.read_burst),
+					]))
+			.collect();
+
+		tables.push(Table::new(Section::ExternalMemory, "External memory",
+			&["Memory", "Port", "Bandwidth (MB/s)", "Write burst", "Read burst"], rows));
+	}
+
+	if report.includes(Section::GlobalMemory)
+	{
+		tables.push(module_instance_table(Section::GlobalMemory, "Global memory", &report.global_memory,
+			markup));
+	}
+	if report.includes(Section::LocalMemory)
+	{
+		tables.push(module_instance_table(Section::LocalMemory, "Local memory", &report.local_memory,
+			markup));
+	}
+	if report.includes(Section::Channel)
+	{
+		tables.push(module_instance_table(Section::Channel, "Channel", &report.channel, markup));
+	}
+	if report.includes(Section::Loop)
+	{
+		tables.push(module_instance_table(Section::Loop, "Loop", &report.loops, markup));
+	}
+	if report.includes(Section::Insights) && !report.insights.is_empty()
+	{
+		let rows = report.insights.iter()
+			.enumerate()
+			.map(|(rank, insight)| vec![
+				(rank + 1).to_string(),
+				(markup.escape)(insight.memory_type),
+				(markup.location)(&insight.source_files),
+				(markup.code)(&insight.kernel),
+				format!("{:.2}", insight.impact),
+				(markup.escape)(&insight.finding),
+				(markup.escape)(&insight.explanation),
+			])
+			.collect();
+
+		tables.push(Table::new(Section::Insights, "Insights",
+			&["Rank", "Section", "Source location", "Kernel", "Impact (%)", "Finding", "Likely cause"], rows));
+	}
+
+	tables
+}
+
+/// Header and rows of a table with one row per module instance group, or per kernel and unroll
+/// instance if expanded, followed by a row per run and their mean and standard deviation if broken
+/// down per run, and a column for every metric available in the section
+fn module_instance_table<Metrics: MetricValues>(section: Section, title: &'static str,
+	groups: &[ModuleInstanceGroup<Metrics>], markup: &Markup) -> Table
+{
+	let expanded = groups.iter().any(|group| group.kernels.is_some());
+	let per_run = groups.iter().any(|group| group.runs.is_some());
+
+	// Cells before the metrics with the values of every metric of `Metric::ALL`
+	let rows = groups.iter()
+		.flat_map(|group|
+			{
+				let location = (markup.location)(&group.source_files);
+				let cells = |kernel: &str, instance: String, run: String|
+					std::iter::once(location.clone())
+						.chain(expanded.then(|| vec![kernel.to_owned(), instance]).into_iter().flatten())
+						.chain(per_run.then_some(run))
+						.collect::<Vec<_>>();
+
+				let mut rows = match &group.kernels
+				{
+					Some(kernels) => kernels.iter()
+						.flat_map(|kernel|
+							kernel.instances.iter().enumerate()
+								.map(|(id, metrics)|
+									{
+										let instance = if kernel.instances.len() > 1
+										{
+											(id + 1).to_string()
+										}
+										else
+										{
+											String::new()
+										};
+
+										let kernel = (markup.code)(&kernel.name);
+
+										(cells(&kernel, instance, String::new()), values(metrics))
+									})
+								.collect::<Vec<_>>())
+						.collect::<Vec<_>>(),
+					None => vec![(cells("", String::new(), String::new()), values(&group.metrics))],
+				};
+
+				if let Some(runs) = &group.runs
+				{
+					let statistics = |statistic: fn(&MetricStatistics) -> f32|
+						Metric::ALL.iter()
+							.map(|&metric|
+								runs.statistics.iter()
+									.find(|statistics| statistics.metric == metric)
+									.map(statistic))
+							.collect::<Vec<_>>();
+
+					rows.extend(runs.metrics.iter().enumerate()
+						.filter_map(|(id, metrics)|
+							metrics.as_ref()
+								.map(|metrics|
+									(cells("", String::new(), (id + 1).to_string()), values(metrics)))));
+					rows.push((cells("", String::new(), "mean".to_owned()),
+						statistics(|statistics| statistics.mean)));
+					rows.push((cells("", String::new(), "standard deviation".to_owned()),
+						statistics(|statistics| statistics.standard_deviation)));
+				}
+
+				rows
+			})
+		.collect::<Vec<_>>();
+
+	let metrics = Metric::ALL.iter().enumerate()
+		.filter(|&(index, _)| rows.iter().any(|(_, values)| values[index].is_some()))
+		.collect::<Vec<_>>();
+
+	let header = std::iter::once("Source location")
+		.chain(expanded.then_some(["Kernel", "Instance"]).into_iter().flatten())
+		.chain(per_run.then_some("Run"))
+		.map(|column| column.to_owned())
+		.chain(metrics.iter()
+			.map(|(_, metric)|
+				match metric.unit().trim()
+				{
+					"" => metric.label().to_owned(),
+					unit => format!("{} ({})", metric.label(), unit),
+				}))
+		.collect();
+
+	let rows = rows.into_iter()
+		.map(|(cells, values)|
+			cells.into_iter()
+				.chain(metrics.iter()
+					.map(|&(index, _)| values[index].map(|value| format!("{:.2}", value)).unwrap_or_default()))
+				.collect())
+		.collect();
+
+	Table { section, title, header, rows }
+}
+
+/// Values of the metrics in the order of `Metric::ALL`
+fn values<Metrics: MetricValues>(metrics: &Metrics) -> Vec<Option<f32>>
+{
+	Metric::ALL.iter().map(|&metric| metrics.value(metric)).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	#[test]
+	fn write_json_omits_the_excluded_sections()
+	{
+		let profile = fixtures::load(fixtures::example());
+		let settings = Settings { sections: Some(vec![Section::Kernels, Section::Loop]), ..Default::default() };
+
+		let mut output = Vec::new();
+		write(&mut output, &profile, &Report::new(&profile, &settings), &settings, OutputFormat::Json).unwrap();
+
+		let value = serde_json::from_slice::<serde_json::Value>(&output).unwrap();
+
+		assert_eq!(value.as_object().unwrap().keys().collect::<Vec<_>>(),
+			vec!["json_type", "versions", "kernels", "loop"]);
+	}
+
+	#[test]
+	fn tables_cover_the_selected_sections()
+	{
+		let profile = fixtures::load(fixtures::example());
+		let settings = Settings
+		{
+			sections: Some(vec![Section::Loop, Section::Boards, Section::Insights]),
+			..Default::default()
+		};
+
+		let markup = Markup { escape: str::to_owned, code: str::to_owned, location: |_| String::new() };
+		let tables = tables(&Report::new(&profile, &settings), &markup);
+
+		// Insights without any finding are omitted
+		assert_eq!(tables.iter().map(|table| table.section).collect::<Vec<_>>(),
+			vec![Section::Boards, Section::Loop]);
+	}
+}
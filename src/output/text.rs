@@ -0,0 +1,618 @@
+use crate::data_model::FileReference;
+use crate::diff::*;
+use crate::metrics::{Metric, MetricValues};
+use crate::report::*;
+use crate::timeline::ModuleInstanceTimeline;
+
+pub fn write<Writer: std::io::Write>(writer: &mut Writer, report: &Report) -> std::io::Result<()>
+{
+	writeln!(writer, "{}: {} (aocx: {})",
+		report.json_type,
+		report.versions.profiler_json_version,
+		report.versions.aocx_version)?;
+
+	if report.includes(Section::Boards)
+	{
+		write_boards(writer, report)?;
+	}
+
+	if report.includes(Section::RunInformation)
+	{
+		write_run_information(writer, report)?;
+	}
+	if report.includes(Section::Kernels)
+	{
+		write_kernels(writer, report)?;
+	}
+	if report.includes(Section::MemoryTransfers)
+	{
+		write_memory_transfers(writer, report)?;
+	}
+	if report.includes(Section::ExternalMemory)
+	{
+		write_external_memory(writer, report)?;
+	}
+
+	if report.includes(Section::GlobalMemory)
+	{
+		writeln!(writer, "Global memory:")?;
+		write_module_instances(writer, &report.global_memory)?;
+	}
+	if report.includes(Section::LocalMemory)
+	{
+		writeln!(writer, "Local memory:")?;
+		write_module_instances(writer, &report.local_memory)?;
+	}
+	if report.includes(Section::Channel)
+	{
+		writeln!(writer, "Channel:")?;
+		write_module_instances(writer, &report.channel)?;
+	}
+	if report.includes(Section::Loop)
+	{
+		writeln!(writer, "Loop:")?;
+		write_module_instances(writer, &report.loops)?;
+	}
+
+	if report.includes(Section::Insights)
+	{
+		write_insights(writer, report)?;
+	}
+
+	Ok(())
+}
+
+fn write_boards<Writer: std::io::Write>(writer: &mut Writer, report: &Report) -> std::io::Result<()>
+{
+	writeln!(writer, "Boards:")?;
+
+	for board in report.boards.iter()
+	{
+		writeln!(writer, "\tType: {}", board.board_type)?;
+		writeln!(writer, "\tGlobal memory:")?;
+
+		for global_memory in board.global_memories.iter()
+		{
+			writeln!(writer, "\t\tMemory {}:", global_memory.name)?;
+			writeln!(writer, "\t\t\tMaximum theoretical global bandwidth: {} MB/s",
+				global_memory.maximum_bandwidth)?;
+			writeln!(writer, "\t\t\tMaximum burst: {}", global_memory.maximum_burst)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn write_run_information<Writer: std::io::Write>(writer: &mut Writer, report: &Report)
+	-> std::io::Result<()>
+{
+	writeln!(writer, "Run information:")?;
+
+	for run_information in report.run_information.iter()
+	{
+		writeln!(writer, "\tFmax: {} MHz", run_information.fmax)?;
+	}
+
+	Ok(())
+}
+
+fn write_kernels<Writer: std::io::Write>(writer: &mut Writer, report: &Report)
+	-> std::io::Result<()>
+{
+	writeln!(writer, "Kernels:")?;
+
+	for kernel in report.kernels.iter()
+	{
+		writeln!(writer, "\tKernel {}:", kernel.name)?;
+		writeln!(writer, "\t\tInvocations: {}", kernel.invocations.len())?;
+		writeln!(writer, "\t\tRuntime: {} total, {} mean, {} minimum, {} maximum",
+			format_time(kernel.total_runtime as f64),
+			format_time(kernel.mean_runtime),
+			format_time(kernel.minimum_runtime as f64),
+			format_time(kernel.maximum_runtime as f64))?;
+
+		for (id, invocation) in kernel.invocations.iter().enumerate()
+		{
+			writeln!(writer, "\t\tInvocation {}{}:", id + 1,
+				if invocation.is_autorun { " (autorun)" } else { "" })?;
+			writeln!(writer, "\t\t\tRuntime: {} (start: {}, end: {})",
+				format_time(invocation.runtime as f64), invocation.start_time, invocation.end_time)?;
+			writeln!(writer, "\t\t\tDevice: {}", format_ids(&invocation.device_ids))?;
+			writeln!(writer, "\t\t\tCommand queue: {}", format_ids(&invocation.command_queue_ids))?;
+			writeln!(writer, "\t\t\tCompute unit: {}", invocation.compute_unit)?;
+			writeln!(writer, "\t\t\tSamples: {}", invocation.number_of_samples)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn write_memory_transfers<Writer: std::io::Write>(writer: &mut Writer, report: &Report)
+	-> std::io::Result<()>
+{
+	writeln!(writer, "Memory transfers:")?;
+	writeln!(writer, "\tTimeline: {}", format_time(report.memory_transfers.timeline as f64))?;
+
+	for group in report.memory_transfers.groups.iter()
+	{
+		writeln!(writer, "\tType {} (device: {}, command queue: {}):",
+			group.type_transfer, group.device_id, group.command_queue_id)?;
+		writeln!(writer, "\t\tCount: {}", group.count)?;
+		writeln!(writer, "\t\tTotal time: {} ({:.2} % of the timeline)",
+			format_time(group.total_time as f64), group.share)?;
+		writeln!(writer, "\t\tLongest:")?;
+
+		for transfer in group.longest.iter()
+		{
+			writeln!(writer, "\t\t\t{} (start: {}, end: {})",
+				format_time(transfer.duration as f64), transfer.start_time, transfer.end_time)?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Formats a duration given in ns
+pub fn format_time(time: f64) -> String
+{
+	format!("{:.3} ms", time / 1e6)
+}
+
+fn format_ids(ids: &[u32]) -> String
+{
+	if ids.is_empty()
+	{
+		"-".to_owned()
+	}
+	else
+	{
+		ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+	}
+}
+
+fn write_external_memory<Writer: std::io::Write>(writer: &mut Writer, report: &Report)
+	-> std::io::Result<()>
+{
+	writeln!(writer, "External memory:")?;
+
+	for external_memory in report.external_memory.iter()
+	{
+		writeln!(writer, "\tMemory {}:", external_memory.name)?;
+
+		for port in external_memory.ports.iter()
+		{
+			writeln!(writer, "\t\tPort {}:", port.port)?;
+			writeln!(writer, "\t\t\tBandwidth: {:.2} MB/s", port.bandwidth)?;
+			writeln!(writer, "\t\t\tWrite burst: {:.2}", port.write_burst)?;
+			writeln!(writer, "\t\t\tRead burst: {:.2}", port.read_burst)?;
+		}
+	}
+
+	Ok(())
+}
+
+fn write_module_instances<Writer, Metrics>(writer: &mut Writer,
+	groups: &[ModuleInstanceGroup<Metrics>]) -> std::io::Result<()>
+where
+	Writer: std::io::Write,
+	Metrics: MetricValues,
+{
+	for group in groups.iter()
+	{
+		writeln!(writer, "{}:", indent(&format_file_references(&group.source_files, 0), 1))?;
+
+		if let Some(kernels) = &group.kernels
+		{
+			for kernel in kernels.iter()
+			{
+				writeln!(writer, "\t\tKernel {}:", kernel.name)?;
+
+				if kernel.instances.len() > 1
+				{
+					for (id, metrics) in kernel.instances.iter().enumerate()
+					{
+						writeln!(writer, "\t\t\tInstance {}:", id + 1)?;
+						write_metrics(writer, metrics, &kernel.instance_series[id], 4)?;
+					}
+				}
+				else if let Some(metrics) = kernel.instances.first()
+				{
+					write_metrics(writer, metrics, &kernel.instance_series[0], 3)?;
+				}
+			}
+		}
+		else
+		{
+			write_metrics(writer, &group.metrics, &group.series, 2)?;
+		}
+
+		if let Some(runs) = &group.runs
+		{
+			writeln!(writer, "\t\tRuns:")?;
+
+			for statistics in runs.statistics.iter()
+			{
+				let unit = statistics.metric.unit();
+
+				let values = runs.metrics.iter()
+					.map(|metrics|
+						metrics.as_ref().and_then(|metrics| metrics.value(statistics.metric))
+							.map(|value| format!("{:.2}{}", value, unit))
+							.unwrap_or_else(|| "-".to_owned()))
+					.collect::<Vec<_>>();
+
+				writeln!(writer, "\t\t\t{}: {} (mean: {:.2}{}, standard deviation: {:.2}{})",
+					statistics.metric.label(), values.join(", "), statistics.mean, unit,
+					statistics.standard_deviation, unit)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn write_insights<Writer: std::io::Write>(writer: &mut Writer, report: &Report) -> std::io::Result<()>
+{
+	if report.insights.is_empty()
+	{
+		return Ok(());
+	}
+
+	writeln!(writer, "Insights:")?;
+
+	for (rank, insight) in report.insights.iter().enumerate()
+	{
+		writeln!(writer, "\t{}. {} in kernel {} (impact: {:.2} %):", rank + 1,
+			capitalize(insight.memory_type), insight.kernel, insight.impact)?;
+		writeln!(writer, "{}", indent(&format_file_references(&insight.source_files, 0), 2))?;
+		writeln!(writer, "\t\t{}: {}", capitalize(&insight.finding), insight.explanation)?;
+	}
+
+	Ok(())
+}
+
+fn capitalize(text: &str) -> String
+{
+	let mut characters = text.chars();
+
+	characters.next()
+		.map(|first| first.to_uppercase().chain(characters).collect())
+		.unwrap_or_default()
+}
+
+pub fn write_timelines<Writer: std::io::Write>(writer: &mut Writer, timelines: &[ModuleInstanceTimeline])
+	-> std::io::Result<()>
+{
+	for timeline in timelines.iter()
+	{
+		writeln!(writer, "{}:", format_file_references(&timeline.source_files, 0))?;
+		writeln!(writer, "\tKernel {}, {} memory, module instance {} (start: {}, end: {}):",
+			timeline.kernel, timeline.memory_type, timeline.module_instance,
+			timeline.start_time, timeline.end_time)?;
+
+		let header = ["Interval", "Start", "End", "Cycles"].iter()
+			.map(|&column| column.to_owned())
+			.chain(timeline.metrics.iter()
+				.map(|metric| format!("{}{}", metric.label(), metric.unit())))
+			.collect::<Vec<_>>();
+
+		let rows = timeline.intervals.iter()
+			.enumerate()
+			.map(|(id, interval)|
+				[
+					(id + 1).to_string(),
+					interval.start_time.to_string(),
+					interval.end_time.to_string(),
+					interval.cycles.map(|cycles| cycles.to_string()).unwrap_or_else(|| "-".to_owned()),
+				].iter().cloned()
+					.chain(interval.values.iter().map(|value| format!("{:.2}", value)))
+					.collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+
+		let widths = header.iter()
+			.enumerate()
+			.map(|(column, title)|
+				rows.iter()
+					.map(|row| row[column].chars().count())
+					.fold(title.chars().count(), usize::max))
+			.collect::<Vec<_>>();
+
+		for row in std::iter::once(&header).chain(rows.iter())
+		{
+			writeln!(writer, "\t\t{}", row.iter()
+				.zip(widths.iter())
+				.map(|(cell, &width)| format!("{:>width$}", cell, width = width))
+				.collect::<Vec<_>>().join("  "))?;
+		}
+	}
+
+	Ok(())
+}
+
+pub fn write_diff<Writer: std::io::Write>(writer: &mut Writer, diff: &Diff) -> std::io::Result<()>
+{
+	writeln!(writer, "Global memory:")?;
+	write_section_diff(writer, &diff.global_memory)?;
+	writeln!(writer, "Local memory:")?;
+	write_section_diff(writer, &diff.local_memory)?;
+	writeln!(writer, "Channel:")?;
+	write_section_diff(writer, &diff.channel)?;
+	writeln!(writer, "Loop:")?;
+	write_section_diff(writer, &diff.loops)?;
+
+	Ok(())
+}
+
+fn write_section_diff<Writer: std::io::Write>(writer: &mut Writer, section_diff: &SectionDiff)
+	-> std::io::Result<()>
+{
+	let mut previous_source_files = None;
+
+	for instance_diff in section_diff.matched.iter()
+	{
+		if previous_source_files != Some(&instance_diff.key.source_files)
+		{
+			writeln!(writer, "{}:", indent(&format_file_references(
+				&instance_diff.key.source_files, 0), 1))?;
+
+			previous_source_files = Some(&instance_diff.key.source_files);
+		}
+
+		writeln!(writer, "\t\t{}:", format_instance(&instance_diff.key))?;
+
+		for delta in instance_diff.deltas.iter()
+		{
+			let unit = delta.metric.unit();
+
+			writeln!(writer, "\t\t\t{}: {:.2}{} → {:.2}{} ({:+.2}{}, relative: {})",
+				delta.metric.label(),
+				delta.baseline, unit,
+				delta.candidate, unit,
+				delta.absolute(), unit,
+				delta.relative()
+					.map(|relative| format!("{:+.2} %", relative))
+					.unwrap_or_else(|| "n/a".to_owned()))?;
+		}
+	}
+
+	for (title, keys) in [("Only in baseline", &section_diff.only_in_baseline),
+		("Only in candidate", &section_diff.only_in_candidate)].iter()
+	{
+		if keys.is_empty()
+		{
+			continue;
+		}
+
+		writeln!(writer, "\t{}:", title)?;
+
+		for key in keys.iter()
+		{
+			writeln!(writer, "{}:", indent(&format_file_references(&key.source_files, 0), 2))?;
+			writeln!(writer, "\t\t\t{}", format_instance(key))?;
+		}
+	}
+
+	Ok(())
+}
+
+fn format_instance(key: &InstanceKey) -> String
+{
+	match key.instance
+	{
+		Some(instance) => format!("Kernel {} (instance {})", key.kernel, instance),
+		None => format!("Kernel {}", key.kernel),
+	}
+}
+
+fn indent(text: &str, level: usize) -> String
+{
+	let indentation = "\t".repeat(level);
+
+	text.lines()
+		.map(|line| format!("{}{}", indentation, line))
+		.collect::<Vec<_>>().join("\n")
+}
+
+/// Averaged metrics followed by a sparkline of their series
+const SPARKLINE_METRICS: &[Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Bandwidth];
+
+/// Writes the metrics of the set indented, one per line with the sparkline appended where there is
+/// one
+fn write_metrics<Writer, Metrics>(writer: &mut Writer, metrics: &Metrics, series: &Series, level: usize)
+	-> std::io::Result<()>
+where
+	Writer: std::io::Write,
+	Metrics: MetricValues,
+{
+	let indentation = "\t".repeat(level);
+
+	let values = Metric::ALL.iter()
+		.filter_map(|&metric| metrics.value(metric).map(|value| (metric, value)));
+
+	for (metric, value) in values
+	{
+		let sparkline = series.get(&metric)
+			.filter(|_| SPARKLINE_METRICS.contains(&metric))
+			.map(|values| format_sparkline(values, (metric.unit() == " %").then_some(100.)))
+			.unwrap_or_default();
+
+		// The maximum channel depth is a count
+		let precision = if metric == Metric::MaximumChannelDepth { 0 } else { 2 };
+
+		writeln!(writer, "{}{}: {:.precision$}{}{}",
+			indentation, metric.label(), value, metric.unit(), sparkline, precision = precision)?;
+	}
+
+	Ok(())
+}
+
+/// Sparkline of the values after a space, empty without values, scaled to the maximum or the
+/// largest value and averaged over buckets to keep it compact, NaN values leave a gap
+fn format_sparkline(values: &[f32], maximum: Option<f32>) -> String
+{
+	const BARS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+	const MAXIMUM_LENGTH: usize = 40;
+
+	if values.is_empty()
+	{
+		return String::new();
+	}
+
+	let bucket_size = values.len().div_ceil(MAXIMUM_LENGTH);
+
+	let buckets = values.chunks(bucket_size)
+		.map(|bucket| bucket.iter().sum::<f32>() / bucket.len() as f32)
+		.collect::<Vec<_>>();
+
+	let maximum = maximum.unwrap_or_else(|| buckets.iter().copied().fold(0., f32::max));
+
+	let sparkline = buckets.iter()
+		.map(|&value|
+			{
+				if value.is_nan()
+				{
+					return ' ';
+				}
+
+				let level = if maximum > 0. && value.is_finite()
+				{
+					(value / maximum * (BARS.len() - 1) as f32).round().clamp(0., (BARS.len() - 1) as f32)
+				}
+				else
+				{
+					0.
+				};
+
+				BARS[level as usize]
+			})
+		.collect::<String>();
+
+	format!(" {}", sparkline)
+}
+
+pub fn format_file_references(file_references: &[FileReference], level: usize) -> String
+{
+	let preamble = if level > 0
+	{
+		format!("\n{}⮤ ", "  ".repeat(level - 1))
+	}
+	else
+	{
+		String::new()
+	};
+
+	file_references.iter()
+		.map(|file_reference|
+			{
+				let callsite = format_file_references(&file_reference.callsite, level + 1);
+
+				format!("{}{} (line: {}{}){}",
+					preamble,
+					file_reference.file_name.clone().into_os_string().into_string().unwrap(),
+					file_reference.line,
+					file_reference.column_number
+						.map(|column| format!(", column: {}", column))
+						.unwrap_or_default(),
+					callsite)
+			})
+		.collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	#[test]
+	fn write_timelines_aligns_a_table_per_module_instance()
+	{
+		let profile = fixtures::load(fixtures::example());
+		let timelines = crate::timeline::timelines(&profile, &Settings::default(),
+			&"kernel.cpp:10".parse().unwrap());
+
+		let mut output = Vec::new();
+		write_timelines(&mut output, &timelines).unwrap();
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().collect::<Vec<_>>(), vec![
+			"/src/kernel.cpp (line: 10)",
+			"⮤ main.cpp (line: 3):",
+			"\tKernel K, global memory, module instance global (start: 0, end: 2000):",
+			"\t\tInterval  Start   End  Cycles  Occupancy %  Stall %  Idle %  Activity %  Bandwidth MB/s",
+			"\t\t       1      0  1000    1000        50.00    25.00   12.50       50.00          500.00",
+			"\t\t       2   1000  2000    1000       100.00    50.00   25.00      100.00         1000.00",
+		]);
+	}
+
+	#[test]
+	fn write_renders_each_metric_with_its_sparkline()
+	{
+		let settings = Settings
+		{
+			sections: Some(vec![Section::GlobalMemory, Section::Loop]),
+			expand: true,
+			..Default::default()
+		};
+		let report = Report::new(&fixtures::load(fixtures::example()), &settings);
+
+		let mut output = Vec::new();
+		write(&mut output, &report).unwrap();
+
+		// Both sampling intervals take half of the time
+		let sparkline = |first: &str, second: &str| format!("{}{}", first.repeat(20), second.repeat(20));
+
+		assert_eq!(String::from_utf8(output).unwrap().lines().collect::<Vec<_>>(), vec![
+			"profiler: 2021.2.0.268.1 (aocx: 21.4)".to_owned(),
+			"Global memory:".to_owned(),
+			"\t/src/kernel.cpp (line: 10)".to_owned(),
+			"\t⮤ main.cpp (line: 3):".to_owned(),
+			"\t\tKernel K:".to_owned(),
+			format!("\t\t\tOccupancy: 75.00 % {}", sparkline("▅", "█")),
+			format!("\t\t\tStall: 37.50 % {}", sparkline("▃", "▅")),
+			"\t\t\tIdle: 18.75 %".to_owned(),
+			"\t\t\tActivity: 75.00 %".to_owned(),
+			format!("\t\t\tBandwidth: 750.00 MB/s {}", sparkline("▅", "█")),
+			"\t\t\tEfficiency: 75.00 %".to_owned(),
+			"\t\t\tBurst size: 750.00".to_owned(),
+			"\t\t\tCache hit: 50.00 %".to_owned(),
+			"Loop:".to_owned(),
+			"\t/src/kernel.cpp (line: 20):".to_owned(),
+			"\t\tKernel K:".to_owned(),
+			"\t\t\tInstance 1:".to_owned(),
+			format!("\t\t\t\tOccupancy: 100.00 % {}", sparkline("█", "█")),
+			"\t\t\tInstance 2:".to_owned(),
+			format!("\t\t\t\tOccupancy: 0.00 % {}", sparkline("▁", "▁")),
+		]);
+	}
+
+	#[test]
+	fn write_leaves_the_sparklines_out_if_the_samples_were_folded()
+	{
+		let settings = Settings { expand: true, ..Default::default() };
+		let written = |profile: &crate::data_model::Profile|
+		{
+			let mut output = Vec::new();
+			write(&mut output, &Report::new(profile, &settings)).unwrap();
+
+			String::from_utf8(output).unwrap()
+		};
+
+		let folded = serde_json::from_value::<crate::data_model::FoldedProfile>(fixtures::example()).unwrap().0;
+		let sparklines = regex::Regex::new(" [▁▂▃▄▅▆▇█ ]+$").unwrap();
+
+		assert!(!written(&folded).contains('█'));
+		assert_eq!(written(&folded).lines().collect::<Vec<_>>(),
+			written(&fixtures::load(fixtures::example())).lines()
+				.map(|line| sparklines.replace(line, ""))
+				.collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn format_sparkline_scales_buckets_and_leaves_gaps()
+	{
+		assert_eq!(format_sparkline(&[], None), "");
+		assert_eq!(format_sparkline(&[0., 50., f32::NAN, 100.], Some(100.)), " ▁▅ █");
+		assert_eq!(format_sparkline(&[1., 2.], None), " ▅█");
+		assert_eq!(format_sparkline(&[100.; 80], Some(100.)), format!(" {}", "█".repeat(40)));
+	}
+}
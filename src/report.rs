@@ -0,0 +1,1083 @@
+use crate::data_model::*;
+use crate::metrics::*;
+use crate::source_pattern::SourcePattern;
+
+/// Selects which parts of a profile are considered and how fine-grained they are broken down
+#[derive(Debug, Clone, Default)]
+pub struct Settings
+{
+	/// Kernels to be considered (all kernels if absent), see `kernel_pattern`
+	pub kernels: Option<Vec<regex::Regex>>,
+	/// Kernels not to be considered, even if selected
+	pub excluded_kernels: Vec<regex::Regex>,
+	/// Source locations of the module instances to be considered (all if empty)
+	pub sources: Vec<SourcePattern>,
+	/// Break the module instances down per kernel and unroll instance
+	pub expand: bool,
+	/// Number of the highest ranked insights to be listed
+	pub insights: usize,
+	/// Orders the module instances of each section by the metric, descending unless ascending
+	pub sort_by: Option<Metric>,
+	pub ascending: bool,
+	/// Lists only the first module instances of each section
+	pub top: Option<usize>,
+	/// Sections to be included (all sections if absent)
+	pub sections: Option<Vec<Section>>,
+}
+
+/// Selectable part of the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section
+{
+	Boards,
+	RunInformation,
+	Kernels,
+	MemoryTransfers,
+	ExternalMemory,
+	GlobalMemory,
+	LocalMemory,
+	Channel,
+	Loop,
+	Insights,
+}
+
+impl Section
+{
+	pub const ALL: &'static [Section] = &[
+		Section::Boards,
+		Section::RunInformation,
+		Section::Kernels,
+		Section::MemoryTransfers,
+		Section::ExternalMemory,
+		Section::GlobalMemory,
+		Section::LocalMemory,
+		Section::Channel,
+		Section::Loop,
+		Section::Insights,
+	];
+
+	pub const VARIANTS: &'static [&'static str] = &["boards", "run", "kernels", "transfers", "external",
+		"global", "local", "channel", "loop", "insights"];
+
+	/// Key of the section in the JSON report
+	pub fn key(&self) -> &'static str
+	{
+		match self
+		{
+			Section::Boards => "boards",
+			Section::RunInformation => "run_information",
+			Section::Kernels => "kernels",
+			Section::MemoryTransfers => "memory_transfers",
+			Section::ExternalMemory => "external_memory",
+			Section::GlobalMemory => "global_memory",
+			Section::LocalMemory => "local_memory",
+			Section::Channel => "channel",
+			Section::Loop => "loop",
+			Section::Insights => "insights",
+		}
+	}
+}
+
+impl std::str::FromStr for Section
+{
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		Self::VARIANTS.iter().position(|name| *name == string)
+			.map(|index| Self::ALL[index])
+			.ok_or_else(|| format!("unknown section: {}", string))
+	}
+}
+
+impl Settings
+{
+	pub fn considers(&self, kernel: &Kernel) -> bool
+	{
+		self.kernels.as_ref()
+			.map(|kernels| kernels.iter().any(|pattern| pattern.is_match(&kernel.name)))
+			.unwrap_or(true)
+			&& !self.excluded_kernels.iter().any(|pattern| pattern.is_match(&kernel.name))
+	}
+
+	pub fn includes(&self, section: Section) -> bool
+	{
+		self.sections.as_ref().map(|sections| sections.contains(&section)).unwrap_or(true)
+	}
+
+	pub fn considers_source(&self, source_files: &[FileReference]) -> bool
+	{
+		self.sources.is_empty() || self.sources.iter().any(|source| source.matches(source_files))
+	}
+}
+
+/// Regular expression that has to match the whole kernel name, hence plain names select exactly
+/// the kernel, e.g. `.*Producer.*` selects all producers
+pub fn kernel_pattern(pattern: &str) -> Result<regex::Regex, regex::Error>
+{
+	regex::Regex::new(&format!("^(?:{})$", pattern))
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Report
+{
+	pub json_type: String,
+	pub versions: Versions,
+	pub boards: Vec<BoardSummary>,
+	pub run_information: Vec<RunInformationSummary>,
+	pub kernels: Vec<KernelSummary>,
+	pub memory_transfers: MemoryTransfersSummary,
+	pub external_memory: Vec<ExternalMemorySummary>,
+	pub global_memory: Vec<ModuleInstanceGroup<GlobalMetrics>>,
+	pub local_memory: Vec<ModuleInstanceGroup<LocalMetrics>>,
+	pub channel: Vec<ModuleInstanceGroup<ChannelMetrics>>,
+	#[serde(rename = "loop")]
+	pub loops: Vec<ModuleInstanceGroup<LoopMetrics>>,
+	pub insights: Vec<crate::insights::Insight>,
+	/// Sections included by the settings, the others are left empty
+	#[serde(skip)]
+	pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BoardSummary
+{
+	pub board_type: String,
+	pub global_memories: Vec<GlobalMemorySummary>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GlobalMemorySummary
+{
+	pub name: String,
+	/// Maximum theoretical global bandwidth (in MB/s)
+	pub maximum_bandwidth: f32,
+	pub maximum_burst: f32,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RunInformationSummary
+{
+	/// Maximum frequency (in MHz)
+	pub fmax: f32,
+}
+
+/// Invocations of a kernel with aggregates over their runtimes (timestamps and times in ns)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KernelSummary
+{
+	pub name: String,
+	pub total_runtime: u64,
+	pub mean_runtime: f64,
+	pub minimum_runtime: u64,
+	pub maximum_runtime: u64,
+	/// Ordered by start time
+	pub invocations: Vec<KernelInvocation>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KernelInvocation
+{
+	pub start_time: u64,
+	pub end_time: u64,
+	pub runtime: u64,
+	pub device_ids: Vec<u32>,
+	pub command_queue_ids: Vec<u32>,
+	pub compute_unit: u32,
+	pub number_of_samples: u32,
+	pub is_autorun: bool,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct MemoryTransfersSummary
+{
+	/// Time from the first start to the last end of any kernel or transfer (in ns)
+	pub timeline: u64,
+	pub groups: Vec<MemoryTransferGroup>,
+}
+
+/// Transfers of the same type on the same device and command queue (times in ns)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MemoryTransferGroup
+{
+	pub type_transfer: String,
+	pub device_id: u32,
+	pub command_queue_id: u32,
+	pub count: usize,
+	pub total_time: u64,
+	/// Share of the timeline spent in the transfers (in %)
+	pub share: f32,
+	/// Longest transfers, in descending order of their duration
+	pub longest: Vec<MemoryTransferSummary>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MemoryTransferSummary
+{
+	pub start_time: u64,
+	pub end_time: u64,
+	pub duration: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExternalMemorySummary
+{
+	pub name: String,
+	pub ports: Vec<PortSummary>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PortSummary
+{
+	pub port: String,
+	/// Time-weighted average bandwidth (in MB/s)
+	pub bandwidth: f32,
+	pub write_burst: f32,
+	pub read_burst: f32,
+}
+
+/// Combined value per time slot of the metrics that have one, for the sparklines, see
+/// `series::combined_series`
+pub type Series = std::collections::BTreeMap<Metric, Vec<f32>>;
+
+/// Metrics of all module instances that originate from the same source location
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModuleInstanceGroup<Metrics>
+{
+	pub source_files: Vec<FileReference>,
+	pub metrics: Metrics,
+	#[serde(skip)]
+	pub series: Series,
+	/// Breakdown per kernel, only present if the settings ask for it
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub kernels: Option<Vec<KernelBreakdown<Metrics>>>,
+	/// Breakdown per run, only present if aggregated over several runs and asked for
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub runs: Option<RunBreakdown<Metrics>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KernelBreakdown<Metrics>
+{
+	pub name: String,
+	/// Metrics of all unroll instances within the kernel
+	pub metrics: Metrics,
+	#[serde(skip)]
+	pub series: Series,
+	/// Metrics per unroll instance, ordered by the name of the module instance
+	pub instances: Vec<Metrics>,
+	/// Series per unroll instance, in the order of the instances
+	#[serde(skip)]
+	pub instance_series: Vec<Series>,
+}
+
+/// Metrics of a module instance group in each of the aggregated runs
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RunBreakdown<Metrics>
+{
+	/// In the order of the runs, absent if the run lacks the module instance group
+	pub metrics: Vec<Option<Metrics>>,
+	/// Spread of each available metric over the runs with the module instance group
+	pub statistics: Vec<MetricStatistics>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MetricStatistics
+{
+	pub metric: Metric,
+	pub mean: f32,
+	/// Sample standard deviation, zero for a single run
+	pub standard_deviation: f32,
+}
+
+impl Report
+{
+	/// Whether the section was built, the writers skip the others
+	pub fn includes(&self, section: Section) -> bool
+	{
+		self.sections.contains(&section)
+	}
+
+	pub fn new(profile: &Profile, settings: &Settings) -> Self
+	{
+		let mut report = Report
+		{
+			json_type: profile.json_type.clone(),
+			versions: profile.versions.clone(),
+			boards: build_if(settings.includes(Section::Boards), || boards(profile)),
+			run_information: build_if(settings.includes(Section::RunInformation),
+				|| run_information(profile)),
+			kernels: build_if(settings.includes(Section::Kernels), || kernels(profile, settings)),
+			memory_transfers: build_if(settings.includes(Section::MemoryTransfers),
+				|| memory_transfers(profile)),
+			external_memory: build_if(settings.includes(Section::ExternalMemory),
+				|| external_memory(profile, settings)),
+			global_memory: build_if(settings.includes(Section::GlobalMemory),
+				|| global_memory_module_instances(profile, settings)),
+			local_memory: build_if(settings.includes(Section::LocalMemory),
+				|| local_memory_module_instances(profile, settings)),
+			channel: build_if(settings.includes(Section::Channel),
+				|| channel_module_instances(profile, settings)),
+			loops: build_if(settings.includes(Section::Loop), || loop_module_instances(profile, settings)),
+			insights: Vec::new(),
+			sections: Section::ALL.iter().copied().filter(|&section| settings.includes(section)).collect(),
+		};
+
+		// The insights rank all module instances of the selected sections per kernel, weighted by the
+		// runtimes of the kernels and the maximum burst of the boards
+		if settings.insights > 0 && settings.includes(Section::Insights)
+		{
+			const MODULE_INSTANCE_SECTIONS: &[Section] =
+				&[Section::GlobalMemory, Section::LocalMemory, Section::Channel, Section::Loop];
+
+			// Selecting none of the module instance sections keeps the insights on all of them
+			let sections = settings.sections.as_ref()
+				.filter(|sections| MODULE_INSTANCE_SECTIONS.iter().any(|section| sections.contains(section)))
+				.map(|sections|
+					sections.iter().copied()
+						.chain([Section::Boards, Section::Kernels].iter().copied())
+						.collect::<Vec<_>>());
+
+			report.insights = if settings.expand && settings.top.is_none() && settings.sections.is_none()
+			{
+				crate::insights::insights(&report, settings.insights)
+			}
+			else
+			{
+				let expanded = Settings
+				{
+					expand: true,
+					insights: 0,
+					top: None,
+					sections,
+					..settings.clone()
+				};
+
+				crate::insights::insights(&Report::new(profile, &expanded), settings.insights)
+			};
+		}
+
+		report
+	}
+
+	/// Reports of the runs to break a report of their merged profiles down with, see `add_runs` and
+	/// `crate::aggregate::merge`
+	pub fn runs(profiles: &[Profile], settings: &Settings) -> Vec<Report>
+	{
+		let settings = Settings
+		{
+			expand: false,
+			insights: 0,
+			sort_by: None,
+			top: None,
+			..settings.clone()
+		};
+
+		profiles.iter()
+			.map(|profile| Report::new(profile, &settings))
+			.collect()
+	}
+
+	/// Breaks the module instance groups down per run
+	pub fn add_runs(&mut self, runs: &[Report])
+	{
+		add_group_runs(&mut self.global_memory, runs.iter().map(|run| &run.global_memory));
+		add_group_runs(&mut self.local_memory, runs.iter().map(|run| &run.local_memory));
+		add_group_runs(&mut self.channel, runs.iter().map(|run| &run.channel));
+		add_group_runs(&mut self.loops, runs.iter().map(|run| &run.loops));
+	}
+}
+
+fn add_group_runs<'a, Metrics, Runs>(groups: &mut [ModuleInstanceGroup<Metrics>], runs: Runs)
+where
+	Metrics: MetricValues + Clone + 'a,
+	Runs: Iterator<Item = &'a Vec<ModuleInstanceGroup<Metrics>>>,
+{
+	let runs = runs
+		.map(|run|
+			run.iter()
+				.map(|group| (&group.source_files, &group.metrics))
+				.collect::<std::collections::BTreeMap<_, _>>())
+		.collect::<Vec<_>>();
+
+	for group in groups.iter_mut()
+	{
+		let metrics = runs.iter()
+			.map(|run| run.get(&group.source_files).map(|&metrics| metrics.clone()))
+			.collect::<Vec<_>>();
+
+		let statistics = Metric::ALL.iter()
+			.filter_map(|&metric|
+				{
+					let values = metrics.iter().flatten()
+						.filter_map(|metrics| metrics.value(metric))
+						.collect::<Vec<_>>();
+
+					if values.is_empty()
+					{
+						return None;
+					}
+
+					let count = values.len() as f32;
+					let mean = values.iter().sum::<f32>() / count;
+					let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f32>()
+						/ (count - 1.).max(1.);
+
+					Some(MetricStatistics
+					{
+						metric,
+						mean,
+						standard_deviation: variance.sqrt(),
+					})
+				})
+			.collect();
+
+		group.runs = Some(RunBreakdown { metrics, statistics });
+	}
+}
+
+fn build_if<Section, Build>(included: bool, build: Build) -> Section
+where
+	Section: Default,
+	Build: FnOnce() -> Section,
+{
+	if included
+	{
+		build()
+	}
+	else
+	{
+		Section::default()
+	}
+}
+
+fn boards(profile: &Profile) -> Vec<BoardSummary>
+{
+	profile.boards.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Board(board) => Some(board),
+				_ => None
+			})
+		.map(|board|
+			{
+				let global_memories = board.children.iter()
+					.filter_map(|child|
+						match child
+						{
+							Child::GlobalMemory(global_memory) => Some(global_memory),
+							_ => None
+						})
+					.map(|global_memory|
+						GlobalMemorySummary
+						{
+							name: global_memory.global_memory_name.clone(),
+							maximum_bandwidth: global_memory.maximum_theoretical_global_memory_bandwidth,
+							maximum_burst: global_memory.maximum_burst_count,
+						})
+					.collect();
+
+				BoardSummary
+				{
+					board_type: board.board_type.clone(),
+					global_memories,
+				}
+			})
+		.collect()
+}
+
+fn run_information(profile: &Profile) -> Vec<RunInformationSummary>
+{
+	profile.run_information.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::RunInformation(run_information) => Some(run_information),
+				_ => None
+			})
+		.map(|run_information| RunInformationSummary { fmax: run_information.fmax })
+		.collect()
+}
+
+fn kernels(profile: &Profile, settings: &Settings) -> Vec<KernelSummary>
+{
+	let structured_kernels = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel))
+		.fold(std::collections::BTreeMap::new(), |mut map, kernel|
+			{
+				map.entry(&kernel.name).or_insert_with(Vec::new).push(kernel);
+
+				map
+			});
+
+	structured_kernels.into_iter()
+		.map(|(name, mut kernels)|
+			{
+				kernels.sort_by_key(|kernel| (kernel.start_time, kernel.end_time));
+
+				let invocations = kernels.into_iter()
+					.map(|kernel|
+						KernelInvocation
+						{
+							start_time: kernel.start_time,
+							end_time: kernel.end_time,
+							runtime: kernel.end_time - kernel.start_time,
+							device_ids: kernel.device_ids.clone(),
+							command_queue_ids: kernel.command_queue_ids.clone(),
+							compute_unit: kernel.compute_unit,
+							number_of_samples: kernel.num_samples,
+							is_autorun: kernel.is_autorun,
+						})
+					.collect::<Vec<_>>();
+
+				let runtimes = invocations.iter().map(|invocation| invocation.runtime);
+				let total_runtime = runtimes.clone().sum::<u64>();
+
+				KernelSummary
+				{
+					name: name.clone(),
+					total_runtime,
+					mean_runtime: total_runtime as f64 / invocations.len() as f64,
+					minimum_runtime: runtimes.clone().min().unwrap_or_default(),
+					maximum_runtime: runtimes.max().unwrap_or_default(),
+					invocations,
+				}
+			})
+		.collect()
+}
+
+fn memory_transfers(profile: &Profile) -> MemoryTransfersSummary
+{
+	const NUMBER_OF_LONGEST: usize = 3;
+
+	let memory_transfers = profile.memory_transfers.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::MemoryTransfers(memory_transfers) => Some(memory_transfers),
+				_ => None
+			})
+		.collect::<Vec<_>>();
+
+	let kernel_times = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some((kernel.start_time, kernel.end_time)),
+				_ => None
+			});
+
+	let (first_start_time, last_end_time) = memory_transfers.iter()
+		.map(|memory_transfer| (memory_transfer.start_time, memory_transfer.end_time))
+		.chain(kernel_times)
+		.fold((u64::MAX, u64::MIN), |(first_start_time, last_end_time), (start_time, end_time)|
+			(first_start_time.min(start_time), last_end_time.max(end_time)));
+
+	let timeline = last_end_time.saturating_sub(first_start_time);
+
+	let structured_transfers = memory_transfers.into_iter()
+		.fold(std::collections::BTreeMap::new(), |mut map, memory_transfer|
+			{
+				map.entry((&memory_transfer.type_transfer, memory_transfer.device_id,
+						memory_transfer.command_queue_id))
+					.or_insert_with(Vec::new).push(memory_transfer);
+
+				map
+			});
+
+	let groups = structured_transfers.into_iter()
+		.map(|((type_transfer, device_id, command_queue_id), memory_transfers)|
+			{
+				let mut transfers = memory_transfers.into_iter()
+					.map(|memory_transfer|
+						MemoryTransferSummary
+						{
+							start_time: memory_transfer.start_time,
+							end_time: memory_transfer.end_time,
+							duration: memory_transfer.end_time - memory_transfer.start_time,
+						})
+					.collect::<Vec<_>>();
+
+				let count = transfers.len();
+				let total_time = transfers.iter().map(|transfer| transfer.duration).sum::<u64>();
+
+				transfers.sort_by_key(|transfer| std::cmp::Reverse(transfer.duration));
+				transfers.truncate(NUMBER_OF_LONGEST);
+
+				MemoryTransferGroup
+				{
+					type_transfer: type_transfer.clone(),
+					device_id,
+					command_queue_id,
+					count,
+					total_time,
+					share: total_time as f32 / timeline as f32 * 100.,
+					longest: transfers,
+				}
+			})
+		.collect();
+
+	MemoryTransfersSummary
+	{
+		timeline,
+		groups,
+	}
+}
+
+fn external_memory(profile: &Profile, settings: &Settings) -> Vec<ExternalMemorySummary>
+{
+	let structured_bandwidths = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel))
+		.flat_map(|kernel|
+			kernel.children.iter()
+				.filter_map(move |child|
+					match child
+					{
+						Child::ExternalMemory(external_memory) => Some((kernel, external_memory)),
+						_ => None
+					}))
+		.map(|(kernel, external_memory)|
+			{
+				let intervals = std::iter::once(&kernel.start_time)
+					.chain(kernel.sample_timestamps.iter())
+					.zip(kernel.sample_timestamps.iter());
+
+				let samples = external_memory.global_used_bandwidth.iter()
+					.zip(external_memory.average_write_burst.iter())
+					.zip(external_memory.average_read_burst.iter());
+
+				let (bandwidth, write_burst, read_burst) = intervals.zip(samples)
+					.fold((0., 0., 0.),
+						|(bandwidth_sum, write_burst_sum, read_burst_sum),
+							((start_time, end_time), ((bandwidth, write_burst), read_burst))|
+						{
+							let time = (end_time - start_time) as f32;
+
+							(bandwidth_sum + time * bandwidth,
+								write_burst_sum + time * write_burst,
+								read_burst_sum + time * read_burst)
+						});
+
+				let time = kernel.end_time - kernel.start_time;
+
+				((&external_memory.name, &external_memory.port), time, bandwidth,
+					write_burst, read_burst)
+			})
+		.fold(std::collections::BTreeMap::new(),
+			|mut map, ((name, port), time, bandwidth, write_burst, read_burst)|
+			{
+				let (ref mut time_sum,
+					(ref mut bandwidth_sum, ref mut write_burst_sum, ref mut read_burst_sum)) =
+						map.entry(name).or_insert_with(std::collections::BTreeMap::new)
+							.entry(port).or_insert((0, (0., 0., 0.)));
+
+				*time_sum += time;
+				*bandwidth_sum += bandwidth;
+				*write_burst_sum += write_burst;
+				*read_burst_sum += read_burst;
+
+				map
+			});
+
+	structured_bandwidths.into_iter()
+		.map(|(name, ports)|
+			{
+				let ports = ports.into_iter()
+					.map(|(port, (time_sum, (bandwidth_sum, write_burst_sum, read_burst_sum)))|
+						PortSummary
+						{
+							port: port.clone(),
+							bandwidth: bandwidth_sum / time_sum as f32,
+							write_burst: write_burst_sum / time_sum as f32,
+							read_burst: read_burst_sum / time_sum as f32,
+						})
+					.collect();
+
+				ExternalMemorySummary
+				{
+					name: name.clone(),
+					ports,
+				}
+			})
+		.collect()
+}
+
+fn module_instances<'a, Filter, Compute, Type, Metrics>(profile: &'a Profile, settings: &Settings,
+	filter: Filter, compute: Compute) -> Vec<ModuleInstanceGroup<Metrics>>
+where
+	Filter: Fn(&'a ModuleInstanceDetails) -> Option<&'a Type>,
+	Compute: for<'b> Fn(&'b [(&'a Kernel, &'a Type)]) -> Metrics,
+	Type: 'a,
+	Metrics: MetricValues,
+{
+	let structured_samples = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel))
+		.flat_map(|kernel|
+			kernel.children.iter()
+				.filter_map(|child|
+					match child
+					{
+						Child::ModuleInstance(module_instance) => Some(module_instance),
+						_ => None
+					})
+				.filter(|module_instance| settings.considers_source(&module_instance.source_files))
+				.filter_map(|module_instance| filter(&module_instance.module_instance_details)
+					.map(|module_instance_details| (module_instance, module_instance_details)))
+				.map(move |(module_instance, module_instance_details)|
+					(kernel, module_instance, module_instance_details)))
+		.fold(std::collections::BTreeMap::new(), |mut map, (kernel, module_instance, sample)|
+			{
+				map.entry(&module_instance.source_files).or_insert_with(Vec::new)
+					.push((kernel, module_instance, sample));
+
+				map
+			});
+
+	let mut groups = structured_samples.into_iter()
+		.map(|(source_files, samples)|
+			{
+				let kernels = settings.expand
+					.then(||
+						samples.iter()
+							.fold(std::collections::BTreeMap::new(),
+								|mut map, (kernel, module_instance, sample)|
+								{
+									map.entry(&kernel.name).or_insert_with(std::collections::BTreeMap::new)
+										.entry(&module_instance.name).or_insert_with(Vec::new)
+											.push((*kernel, *module_instance, *sample));
+
+									map
+								})
+							.into_iter()
+							.map(|(kernel_name, unrolls)|
+								{
+									let samples = unrolls.values().flatten().copied().collect::<Vec<_>>();
+
+									KernelBreakdown
+									{
+										name: kernel_name.clone(),
+										metrics: compute(&typed_samples(&samples)),
+										series: series(&samples),
+										instances: unrolls.values()
+											.map(|samples| compute(&typed_samples(samples)))
+											.collect(),
+										instance_series: unrolls.values()
+											.map(|samples| series(samples))
+											.collect(),
+									}
+								})
+							.collect());
+
+				ModuleInstanceGroup
+				{
+					source_files: source_files.clone(),
+					metrics: compute(&typed_samples(&samples)),
+					series: series(&samples),
+					kernels,
+					runs: None,
+				}
+			})
+		.collect::<Vec<_>>();
+
+	if let Some(metric) = settings.sort_by
+	{
+		let order = |left: &Metrics, right: &Metrics| order(left, right, metric, settings.ascending);
+
+		groups.sort_by(|left, right| order(&left.metrics, &right.metrics));
+
+		for kernels in groups.iter_mut().filter_map(|group| group.kernels.as_mut())
+		{
+			kernels.sort_by(|left, right| order(&left.metrics, &right.metrics));
+		}
+	}
+
+	if let Some(top) = settings.top
+	{
+		groups.truncate(top);
+	}
+
+	groups
+}
+
+/// Samples of the module instance type, as the metrics are computed from
+fn typed_samples<'a, Type>(samples: &[(&'a Kernel, &'a ModuleInstance, &'a Type)])
+	-> Vec<(&'a Kernel, &'a Type)>
+{
+	samples.iter()
+		.map(|&(kernel, _, sample)| (kernel, sample))
+		.collect()
+}
+
+fn series<Type>(samples: &[(&Kernel, &ModuleInstance, &Type)]) -> Series
+{
+	/// Width of the sparklines
+	const SLOTS: usize = 40;
+
+	let samples = samples.iter()
+		.map(|&(kernel, module_instance, _)| (kernel, &module_instance.module_instance_details))
+		.collect::<Vec<_>>();
+
+	Metric::ALL.iter()
+		.filter_map(|&metric|
+			crate::series::combined_series(&samples, metric, SLOTS).map(|series| (metric, series)))
+		.collect()
+}
+
+/// Module instances without the metric come last
+fn order<Metrics: MetricValues>(left: &Metrics, right: &Metrics, metric: Metric, ascending: bool)
+	-> std::cmp::Ordering
+{
+	compare_values(left.value(metric), right.value(metric), ascending)
+}
+
+/// Total order of metric values, where missing values and NaN, e.g. of kernels without
+/// `total_cycles_between_samples`, come last regardless of the direction
+pub fn compare_values(left: Option<f32>, right: Option<f32>, ascending: bool) -> std::cmp::Ordering
+{
+	match (left.filter(|value| !value.is_nan()), right.filter(|value| !value.is_nan()))
+	{
+		(Some(left), Some(right)) =>
+		{
+			let ordering = left.total_cmp(&right);
+
+			if ascending { ordering } else { ordering.reverse() }
+		}
+		(Some(_), None) => std::cmp::Ordering::Less,
+		(None, Some(_)) => std::cmp::Ordering::Greater,
+		(None, None) => std::cmp::Ordering::Equal,
+	}
+}
+
+fn global_memory_module_instances(profile: &Profile, settings: &Settings)
+	-> Vec<ModuleInstanceGroup<GlobalMetrics>>
+{
+	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Global>
+	{
+		match module_instance_details
+		{
+			ModuleInstanceDetails::Global(ref sample) => Some(sample),
+			_ => None
+		}
+	}
+
+	module_instances(profile, settings, filter, compute_global)
+}
+
+fn local_memory_module_instances(profile: &Profile, settings: &Settings)
+	-> Vec<ModuleInstanceGroup<LocalMetrics>>
+{
+	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Local>
+	{
+		match module_instance_details
+		{
+			ModuleInstanceDetails::Local(ref sample) => Some(sample),
+			_ => None
+		}
+	}
+
+	module_instances(profile, settings, filter, compute_local)
+}
+
+fn channel_module_instances(profile: &Profile, settings: &Settings)
+	-> Vec<ModuleInstanceGroup<ChannelMetrics>>
+{
+	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Channel>
+	{
+		match module_instance_details
+		{
+			ModuleInstanceDetails::Channel(ref sample) => Some(sample),
+			_ => None
+		}
+	}
+
+	module_instances(profile, settings, filter, compute_channel)
+}
+
+fn loop_module_instances(profile: &Profile, settings: &Settings)
+	-> Vec<ModuleInstanceGroup<LoopMetrics>>
+{
+	fn filter(module_instance_details: &ModuleInstanceDetails) -> Option<&Loop>
+	{
+		match module_instance_details
+		{
+			ModuleInstanceDetails::Loop(ref sample) => Some(sample),
+			_ => None
+		}
+	}
+
+	module_instances(profile, settings, filter, compute_loop)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn kernel(name: &str, start_time: u64, lines: impl Iterator<Item = u32>, with_cycles: bool)
+		-> serde_json::Value
+	{
+		let children = lines
+			.map(|line|
+				{
+					let mut details = fixtures::details("__local", &[500, 500]);
+					details["stall_samples"] = serde_json::json!([line * 7 % 50, 0]);
+
+					fixtures::module_instance(&format!("local{}", line), "/src/kernel.cpp", line, details)
+				})
+			.collect();
+
+		let sample_timestamps = [start_time + 1000, start_time + 2000];
+		let mut kernel = fixtures::kernel(name, start_time, &sample_timestamps, children);
+
+		if !with_cycles
+		{
+			kernel.as_object_mut().unwrap().remove("total_cycles_between_samples");
+		}
+
+		kernel
+	}
+
+	#[test]
+	fn sort_by_places_nan_last()
+	{
+		// Interleaved source lines, every other one in the kernel without cycles, hence NaN
+		let profile = fixtures::load(fixtures::profile(vec![
+			kernel("with_cycles", 0, (0..60).step_by(2), true),
+			kernel("without_cycles", 5000, (1..60).step_by(2), false),
+		]));
+
+		for &ascending in [false, true].iter()
+		{
+			let settings = Settings
+			{
+				sort_by: Some(Metric::Stall),
+				ascending,
+				..Default::default()
+			};
+
+			let stalls = Report::new(&profile, &settings).local_memory.iter()
+				.map(|group| group.metrics.stall.stall)
+				.collect::<Vec<_>>();
+
+			assert_eq!(stalls.len(), 60);
+			assert!(stalls[..30].iter().all(|stall| !stall.is_nan()));
+			assert!(stalls[30..].iter().all(|stall| stall.is_nan()));
+			assert!(stalls[..30].windows(2).all(|pair|
+				if ascending { pair[0] <= pair[1] } else { pair[0] >= pair[1] }));
+		}
+	}
+
+	#[test]
+	fn kernels_summarize_the_invocations_in_start_order()
+	{
+		let profile = fixtures::load(fixtures::profile(vec![
+			fixtures::kernel("K", 5000, &[6000, 9000], vec![]),
+			fixtures::kernel("L", 0, &[500], vec![]),
+			fixtures::kernel("K", 0, &[1000], vec![]),
+		]));
+
+		let kernels = Report::new(&profile, &Settings::default()).kernels;
+
+		assert_eq!(kernels.iter().map(|kernel| kernel.name.as_str()).collect::<Vec<_>>(), vec!["K", "L"]);
+		assert_eq!((kernels[0].total_runtime, kernels[0].mean_runtime, kernels[0].minimum_runtime,
+			kernels[0].maximum_runtime), (5000, 2500., 1000, 4000));
+		assert_eq!(kernels[0].invocations.iter()
+				.map(|invocation| (invocation.start_time, invocation.runtime, invocation.number_of_samples))
+				.collect::<Vec<_>>(),
+			vec![(0, 1000, 1), (5000, 4000, 2)]);
+		assert_eq!((kernels[1].total_runtime, kernels[1].mean_runtime), (500, 500.));
+	}
+
+	#[test]
+	fn memory_transfers_are_grouped_by_type_device_and_queue()
+	{
+		let mut profile = fixtures::profile(vec![fixtures::kernel("K", 1000, &[2000], vec![])]);
+		profile["memtransfers"]["nodes"] = serde_json::json!([
+			fixtures::memory_transfer("write", 0, 0, 0, 100),
+			fixtures::memory_transfer("write", 0, 0, 200, 500),
+			fixtures::memory_transfer("write", 0, 1, 0, 50),
+			fixtures::memory_transfer("read", 0, 0, 3000, 4000),
+		]);
+
+		let settings = Settings { excluded_kernels: vec![kernel_pattern("K").unwrap()], ..Default::default() };
+		let memory_transfers = Report::new(&fixtures::load(profile), &settings).memory_transfers;
+
+		assert_eq!(memory_transfers.timeline, 4000);
+		assert_eq!(memory_transfers.groups.iter()
+				.map(|group| (group.type_transfer.as_str(), group.device_id, group.command_queue_id, group.count,
+					group.total_time, group.share))
+				.collect::<Vec<_>>(),
+			vec![("read", 0, 0, 1, 1000, 25.), ("write", 0, 0, 2, 400, 10.), ("write", 0, 1, 1, 50, 1.25)]);
+		assert_eq!(memory_transfers.groups[1].longest.iter()
+				.map(|transfer| (transfer.start_time, transfer.duration))
+				.collect::<Vec<_>>(),
+			vec![(200, 300), (0, 100)]);
+	}
+
+	#[test]
+	fn settings_select_kernels_by_whole_name_and_exclude_them()
+	{
+		let profile = fixtures::load(fixtures::profile(vec![
+			fixtures::kernel("Producer", 0, &[1000], vec![]),
+			fixtures::kernel("Producer2", 0, &[1000], vec![]),
+			fixtures::kernel("Consumer", 0, &[1000], vec![]),
+		]));
+
+		let names = |kernels: Option<&[&str]>, excluded_kernels: &[&str]|
+		{
+			let patterns = |patterns: &[&str]|
+				patterns.iter().map(|pattern| kernel_pattern(pattern).unwrap()).collect::<Vec<_>>();
+
+			let settings = Settings
+			{
+				kernels: kernels.map(patterns),
+				excluded_kernels: patterns(excluded_kernels),
+				..Default::default()
+			};
+
+			Report::new(&profile, &settings).kernels.into_iter().map(|kernel| kernel.name).collect::<Vec<_>>()
+		};
+
+		assert_eq!(names(None, &[]), vec!["Consumer", "Producer", "Producer2"]);
+		assert_eq!(names(Some(&["Producer"]), &[]), vec!["Producer"]);
+		assert_eq!(names(Some(&["Producer.*", "Consumer"]), &["Producer2"]), vec!["Consumer", "Producer"]);
+		assert_eq!(names(None, &[".*2|Consumer"]), vec!["Producer"]);
+		assert!(names(Some(&["roducer"]), &[]).is_empty());
+		assert!(kernel_pattern("(").is_err());
+	}
+
+	#[test]
+	fn sections_build_only_the_selected_parts()
+	{
+		let sections = "kernels,loop".split(',').map(|section| section.parse().unwrap()).collect::<Vec<_>>();
+		let settings = Settings { sections: Some(sections), insights: 5, ..Default::default() };
+
+		let report = Report::new(&fixtures::load(fixtures::example()), &settings);
+
+		assert_eq!(report.sections, vec![Section::Kernels, Section::Loop]);
+		assert!(report.includes(Section::Loop) && !report.includes(Section::Insights));
+		assert_eq!((report.kernels.len(), report.loops.len()), (1, 1));
+		assert!(report.global_memory.is_empty() && report.insights.is_empty());
+		assert!("globals".parse::<Section>().is_err());
+	}
+}
@@ -0,0 +1,325 @@
+use crate::data_model::*;
+use crate::metrics::Metric;
+use crate::module_instance_details::*;
+
+/// Sampling intervals of a kernel invocation as pairs of start and end time
+pub fn intervals(kernel: &Kernel) -> Vec<(u64, u64)>
+{
+	std::iter::once(&kernel.start_time)
+		.chain(kernel.sample_timestamps.iter())
+		.zip(kernel.sample_timestamps.iter())
+		.map(|(&start_time, &end_time)| (start_time, end_time))
+		.collect()
+}
+
+/// Value of the metric per sampling interval, if the module instance records it and its samples
+/// were not folded while loading
+pub fn metric_series(kernel: &Kernel, module_instance_details: &ModuleInstanceDetails,
+	metric: Metric) -> Option<Vec<f32>>
+{
+	if folded(module_instance_details)
+	{
+		return None;
+	}
+
+	let cycles = kernel.total_cycles_between_samples.as_deref();
+
+	match metric
+	{
+		Metric::Occupancy => occupancy(module_instance_details)
+			.zip(cycles)
+			.map(|(occupancy, cycles)| percentages(occupancy.occupancy_samples(), cycles)),
+		Metric::Stall => stall(module_instance_details)
+			.zip(cycles)
+			.map(|(stall, cycles)| percentages(stall.stall_samples(), cycles)),
+		Metric::Idle => stall(module_instance_details)
+			.zip(cycles)
+			.map(|(stall, cycles)| percentages(stall.idle_samples(), cycles)),
+		Metric::Activity => stall(module_instance_details)
+			.zip(cycles)
+			.map(|(stall, cycles)| percentages(stall.activity_samples(), cycles)),
+		Metric::Bandwidth => bandwidth(module_instance_details)
+			.map(|bandwidth| bandwidth.bandwidth_samples().to_vec()),
+		Metric::Efficiency => effectiveness(module_instance_details)
+			.map(|effectiveness|
+				effectiveness.bandwidth_effective_samples().iter()
+					.map(|bandwidth_effective| bandwidth_effective * 100.)
+					.collect()),
+		Metric::BurstSize => effectiveness(module_instance_details)
+			.map(|effectiveness| effectiveness.average_burst_size().to_vec()),
+		Metric::CacheHit => effectiveness(module_instance_details)
+			.zip(occupancy(module_instance_details))
+			.filter(|(effectiveness, _)| !effectiveness.cache_hit_samples().is_empty())
+			.map(|(effectiveness, occupancy)|
+				percentages(effectiveness.cache_hit_samples(), occupancy.occupancy_samples())),
+		Metric::AverageChannelDepth => channel_depth(module_instance_details)
+			.map(|channel_depth| channel_depth.average_channel_depth_samples().to_vec()),
+		Metric::MaximumChannelDepth => channel_depth(module_instance_details)
+			.map(|channel_depth|
+				channel_depth.maximum_channel_depth_samples().iter()
+					.map(|&maximum_channel_depth| maximum_channel_depth as f32)
+					.collect()),
+	}
+}
+
+/// Value per slot of several module instances, e.g. the unroll instances and kernel invocations of
+/// a source location, with the time from the first start to the last end of their intervals split
+/// into the slots: the counters relative to the cycles and the bandwidth summed over the concurrent
+/// module instances, both weighted by the overlap of the intervals with the slot
+///
+/// Slots without any interval are NaN. Only the occupancy, stall, idle, activity and bandwidth are
+/// combined, module instances whose samples were folded while loading are left out.
+pub fn combined_series(samples: &[(&Kernel, &ModuleInstanceDetails)], metric: Metric, length: usize)
+	-> Option<Vec<f32>>
+{
+	// Start and end time with the numerator and denominator of the interval
+	let intervals = samples.iter()
+		.filter(|&&(_, module_instance_details)| !folded(module_instance_details))
+		.filter_map(|&(kernel, module_instance_details)|
+			{
+				let counters = match metric
+				{
+					Metric::Occupancy => occupancy(module_instance_details)
+						.map(|occupancy| occupancy.occupancy_samples()),
+					Metric::Stall => stall(module_instance_details).map(|stall| stall.stall_samples()),
+					Metric::Idle => stall(module_instance_details).map(|stall| stall.idle_samples()),
+					Metric::Activity => stall(module_instance_details).map(|stall| stall.activity_samples()),
+					Metric::Bandwidth =>
+					{
+						// Summed over the concurrent module instances, hence relative to the covered time
+						return bandwidth(module_instance_details)
+							.map(|bandwidth|
+								intervals(kernel).into_iter()
+									.zip(bandwidth.bandwidth_samples())
+									.map(|((start_time, end_time), &bandwidth)|
+										{
+											// Timestamps out of order are reported by the validation
+											let length = end_time.saturating_sub(start_time) as f64;
+
+											(start_time, end_time, length * bandwidth as f64, 0.)
+										})
+									.collect::<Vec<_>>());
+					}
+					_ => None,
+				}?;
+
+				Some(intervals(kernel).into_iter()
+					.zip(counters)
+					.zip(kernel.total_cycles_between_samples.as_deref().unwrap_or_default())
+					.map(|(((start_time, end_time), &counter), &cycles)|
+						(start_time, end_time, counter as f64, cycles as f64))
+					.collect::<Vec<_>>())
+			})
+		.collect::<Vec<_>>()
+		.concat();
+
+	if intervals.is_empty()
+	{
+		return None;
+	}
+
+	if metric == Metric::Bandwidth
+	{
+		let covered = union(intervals.iter().map(|&(start_time, end_time, _, _)| (start_time, end_time)))
+			.into_iter()
+			.map(|(start_time, end_time)| (start_time, end_time, 0., end_time.saturating_sub(start_time) as f64));
+
+		let slots = slots(&intervals.iter().copied().chain(covered).collect::<Vec<_>>(), length);
+
+		return Some(slots.into_iter().map(|(bandwidth, time)| (bandwidth / time) as f32).collect());
+	}
+
+	Some(slots(&intervals, length).into_iter()
+		.map(|(counter, cycles)| (counter / cycles * 100.) as f32)
+		.collect())
+}
+
+/// Sums of the numerators and denominators of the intervals per slot, each interval contributes
+/// according to its overlap with the slot
+fn slots(intervals: &[(u64, u64, f64, f64)], length: usize) -> Vec<(f64, f64)>
+{
+	let (first_start_time, last_end_time) = intervals.iter()
+		.fold((u64::MAX, u64::MIN), |(first_start_time, last_end_time), &(start_time, end_time, _, _)|
+			(first_start_time.min(start_time), last_end_time.max(end_time)));
+
+	if first_start_time >= last_end_time
+	{
+		return Vec::new();
+	}
+
+	let slot_time = (last_end_time - first_start_time) as f64 / length as f64;
+	let mut slots = vec![(0., 0.); length];
+
+	for &(start_time, end_time, numerator, denominator) in intervals.iter()
+	{
+		let start = (start_time - first_start_time) as f64;
+		let end = (end_time - first_start_time) as f64;
+
+		if end <= start
+		{
+			continue;
+		}
+
+		let first_slot = (start / slot_time) as usize;
+		let last_slot = ((end / slot_time).ceil() as usize).min(length);
+
+		for (index, slot) in slots.iter_mut().enumerate().take(last_slot).skip(first_slot)
+		{
+			let overlap = end.min((index + 1) as f64 * slot_time) - start.max(index as f64 * slot_time);
+			let share = overlap.max(0.) / (end - start);
+
+			slot.0 += numerator * share;
+			slot.1 += denominator * share;
+		}
+	}
+
+	slots
+}
+
+/// Merges overlapping intervals
+fn union(intervals: impl Iterator<Item = (u64, u64)>) -> Vec<(u64, u64)>
+{
+	let mut intervals = intervals.collect::<Vec<_>>();
+	intervals.sort_unstable();
+
+	intervals.into_iter()
+		.fold(Vec::<(u64, u64)>::new(), |mut union, (start_time, end_time)|
+			{
+				match union.last_mut()
+				{
+					Some(last) if start_time <= last.1 => last.1 = last.1.max(end_time),
+					_ => union.push((start_time, end_time)),
+				}
+
+				union
+			})
+}
+
+fn percentages(numerators: &[u64], denominators: &[u64]) -> Vec<f32>
+{
+	numerators.iter()
+		.zip(denominators.iter())
+		.map(|(&numerator, &denominator)| numerator as f32 / denominator as f32 * 100.)
+		.collect()
+}
+
+fn folded(module_instance_details: &ModuleInstanceDetails) -> bool
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(ref sample) => sample.sample_sums().is_some(),
+		ModuleInstanceDetails::Local(ref sample) => sample.sample_sums().is_some(),
+		ModuleInstanceDetails::Channel(ref sample) => sample.sample_sums().is_some(),
+		ModuleInstanceDetails::Loop(ref sample) => sample.sample_sums().is_some(),
+		ModuleInstanceDetails::Unknown(_) => false,
+	}
+}
+
+fn occupancy(module_instance_details: &ModuleInstanceDetails) -> Option<&dyn Occupancy>
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(ref sample) => Some(sample),
+		ModuleInstanceDetails::Local(ref sample) => Some(sample),
+		ModuleInstanceDetails::Channel(ref sample) => Some(sample),
+		ModuleInstanceDetails::Loop(ref sample) => Some(sample),
+		ModuleInstanceDetails::Unknown(_) => None,
+	}
+}
+
+fn stall(module_instance_details: &ModuleInstanceDetails) -> Option<&dyn Stall>
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(ref sample) => Some(sample),
+		ModuleInstanceDetails::Local(ref sample) => Some(sample),
+		ModuleInstanceDetails::Channel(ref sample) => Some(sample),
+		_ => None
+	}
+}
+
+fn bandwidth(module_instance_details: &ModuleInstanceDetails) -> Option<&dyn Bandwidth>
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(ref sample) => Some(sample),
+		ModuleInstanceDetails::Channel(ref sample) => Some(sample),
+		_ => None
+	}
+}
+
+fn effectiveness(module_instance_details: &ModuleInstanceDetails) -> Option<&dyn Effectiveness>
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(ref sample) => Some(sample),
+		_ => None
+	}
+}
+
+fn channel_depth(module_instance_details: &ModuleInstanceDetails) -> Option<&dyn ChannelDepth>
+{
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Channel(ref sample) => Some(sample),
+		_ => None
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	fn global(occupancy: [u64; 3], bandwidth: [f32; 3]) -> ModuleInstanceDetails
+	{
+		let mut details = fixtures::details("__global", &occupancy);
+		details["bandwidth_samples"] = serde_json::json!(bandwidth);
+
+		serde_json::from_value(details).unwrap()
+	}
+
+	#[test]
+	fn combined_series_weights_the_intervals_by_their_overlap_with_the_slots()
+	{
+		// Intervals of 1000, 2000 and 1000 ns, one slot per 1000 ns
+		let mut kernel = fixtures::kernel("kernel", 0, &[1000, 3000, 4000], vec![]);
+		kernel["total_cycles_between_samples"] = serde_json::json!([100, 200, 100]);
+		let kernel: Kernel = serde_json::from_value(kernel).unwrap();
+
+		let first = global([50, 200, 0], [100., 200., 300.]);
+		let second = global([100, 0, 100], [100., 0., 100.]);
+		let samples = [(&kernel, &first), (&kernel, &second)];
+
+		assert_eq!(combined_series(&samples, Metric::Occupancy, 4), Some(vec![75., 50., 50., 50.]));
+		assert_eq!(combined_series(&samples, Metric::Bandwidth, 4), Some(vec![200., 200., 200., 400.]));
+		assert_eq!(combined_series(&samples, Metric::Efficiency, 4), None);
+		assert_eq!(combined_series(&samples[..1], Metric::Stall, 4), Some(vec![25., 50., 50., 0.]));
+		assert_eq!(combined_series(&samples[..1], Metric::Stall, 2), Some(vec![37.5, 25.]));
+	}
+
+	#[test]
+	fn combined_series_places_the_invocations_on_a_common_time_axis()
+	{
+		let kernel = |start_time: u64|
+			serde_json::from_value::<Kernel>(
+				fixtures::kernel("kernel", start_time, &[start_time + 1000], vec![])).unwrap();
+		let (early, late, concurrent) = (kernel(0), kernel(3000), kernel(3500));
+
+		let details = global([500, 0, 0], [100., 0., 0.]);
+		let samples = [(&early, &details), (&late, &details), (&concurrent, &details)];
+
+		let occupancy = combined_series(&samples[..2], Metric::Occupancy, 4).unwrap();
+
+		assert_eq!((occupancy[0], occupancy[3]), (50., 50.));
+		assert!(occupancy[1].is_nan() && occupancy[2].is_nan());
+
+		// The concurrent invocations overlap from 3500 to 4000 ns
+		let bandwidth = combined_series(&samples, Metric::Bandwidth, 9).unwrap();
+
+		assert_eq!((bandwidth[0], bandwidth[1], bandwidth[6], bandwidth[7], bandwidth[8]),
+			(100., 100., 100., 200., 100.));
+		assert!(bandwidth[2..6].iter().all(|value| value.is_nan()));
+	}
+}
@@ -0,0 +1,177 @@
+use crate::data_model::FileReference;
+
+/// Source location in the form `<file>[:<line>[-<line>]]`, where the file is a glob pattern that
+/// matches any path ending with it, e.g. `kernel.cpp:42`, `include/*.hpp` or `helper.hpp:10-20`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcePattern
+{
+	pub file_name: glob::Pattern,
+	pub lines: Option<std::ops::RangeInclusive<u32>>,
+}
+
+impl SourcePattern
+{
+	/// Checks the file references including their callsite chains
+	pub fn matches(&self, file_references: &[FileReference]) -> bool
+	{
+		file_references.iter()
+			.any(|file_reference|
+				(self.matches_file_name(&file_reference.file_name)
+					&& self.lines.as_ref().map(|lines| lines.contains(&file_reference.line)).unwrap_or(true))
+				|| self.matches(&file_reference.callsite))
+	}
+
+	/// Matches the pattern against every trailing part of the path, as wildcards do not cross
+	/// path separators
+	fn matches_file_name(&self, file_name: &std::path::Path) -> bool
+	{
+		let options = glob::MatchOptions
+		{
+			require_literal_separator: true,
+			..Default::default()
+		};
+
+		let components = file_name.components().collect::<Vec<_>>();
+
+		(0..components.len())
+			.map(|start| components[start..].iter().collect::<std::path::PathBuf>())
+			.any(|suffix| self.file_name.matches_path_with(&suffix, options))
+	}
+}
+
+impl std::str::FromStr for SourcePattern
+{
+	type Err = String;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err>
+	{
+		// Only a tail of digits and dashes is taken as lines, as paths may contain colons, e.g.
+		// `C:\src\kernel.cpp`
+		let split = string.rsplit_once(':')
+			.filter(|(_, lines)| lines.chars().all(|character| character.is_ascii_digit() || character == '-'));
+
+		let (file_name, lines) = match split
+		{
+			Some((file_name, lines)) =>
+			{
+				let parse_line = |line: &str| line.parse::<u32>()
+					.map_err(|error| format!("invalid line in source pattern {}: {}", string, error));
+
+				let lines = match lines.split_once('-')
+				{
+					Some((first, last)) => parse_line(first)?..=parse_line(last)?,
+					None => parse_line(lines)?..=parse_line(lines)?,
+				};
+
+				if lines.is_empty()
+				{
+					return Err(format!("empty line range in source pattern: {}", string));
+				}
+
+				(file_name, Some(lines))
+			}
+			None => (string, None),
+		};
+
+		if file_name.is_empty()
+		{
+			return Err(format!("missing file in source pattern: {}", string));
+		}
+
+		Ok(SourcePattern
+		{
+			file_name: glob::Pattern::new(file_name)
+				.map_err(|error| format!("invalid file in source pattern {}: {}", string, error))?,
+			lines,
+		})
+	}
+}
+
+impl std::fmt::Display for SourcePattern
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}", self.file_name)?;
+
+		match &self.lines
+		{
+			Some(lines) if lines.start() == lines.end() => write!(formatter, ":{}", lines.start()),
+			Some(lines) => write!(formatter, ":{}-{}", lines.start(), lines.end()),
+			None => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	fn file_reference(file_name: &str, line: u32, callsite: Vec<FileReference>) -> FileReference
+	{
+		FileReference
+		{
+			file_name: file_name.into(),
+			line,
+			column_number: None,
+			callsite,
+		}
+	}
+
+	fn pattern(string: &str) -> SourcePattern
+	{
+		string.parse().unwrap()
+	}
+
+	#[test]
+	fn from_str_parses_file_and_lines()
+	{
+		assert_eq!(pattern("kernel.cpp").lines, None);
+		assert_eq!(pattern("kernel.cpp:42").lines, Some(42..=42));
+		assert_eq!(pattern("include/*.hpp:10-20").lines, Some(10..=20));
+
+		for string in ["kernel.cpp", "kernel.cpp:42", "include/*.hpp:10-20"].iter()
+		{
+			assert_eq!(pattern(string).to_string(), *string);
+		}
+
+		for string in ["", ":42", "kernel.cpp:", "kernel.cpp:4-", "kernel.cpp:20-10", "[.cpp"].iter()
+		{
+			assert!(string.parse::<SourcePattern>().is_err(), "{} parsed", string);
+		}
+
+		// Colons followed by anything but lines belong to the file
+		assert_eq!(pattern("C:\\src\\kernel.cpp").file_name.as_str(), "C:\\src\\kernel.cpp");
+		assert_eq!(pattern("C:\\src\\kernel.cpp").lines, None);
+		assert_eq!(pattern("C:\\src\\kernel.cpp:42").file_name.as_str(), "C:\\src\\kernel.cpp");
+		assert_eq!(pattern("C:\\src\\kernel.cpp:42").lines, Some(42..=42));
+		assert_eq!(pattern("a:b.cpp").file_name.as_str(), "a:b.cpp");
+	}
+
+	#[test]
+	fn matches_path_suffixes_and_lines()
+	{
+		let kernel = [file_reference("/src/device/kernel.cpp", 42, vec![])];
+
+		assert!(pattern("kernel.cpp").matches(&kernel));
+		assert!(pattern("device/kernel.cpp:40-50").matches(&kernel));
+		assert!(pattern("/src/*/kernel.cpp:42").matches(&kernel));
+		assert!(!pattern("kernel.cpp:43").matches(&kernel));
+		assert!(!pattern("other/kernel.cpp").matches(&kernel));
+		assert!(!pattern("ernel.cpp").matches(&kernel));
+		// Wildcards do not cross path separators
+		assert!(!pattern("src/*.cpp").matches(&kernel));
+	}
+
+	#[test]
+	fn matches_callsites()
+	{
+		let helper = [file_reference("/include/helper.hpp", 7,
+			vec![file_reference("/src/kernel.cpp", 42, vec![file_reference("/src/main.cpp", 3, vec![])])])];
+
+		assert!(pattern("helper.hpp:7").matches(&helper));
+		assert!(pattern("kernel.cpp:42").matches(&helper));
+		assert!(pattern("main.cpp:3").matches(&helper));
+		assert!(!pattern("kernel.cpp:7").matches(&helper));
+	}
+}
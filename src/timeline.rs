@@ -0,0 +1,171 @@
+use crate::data_model::*;
+use crate::metrics::Metric;
+use crate::report::Settings;
+use crate::series::*;
+use crate::source_pattern::SourcePattern;
+
+/// Metrics listed per sampling interval, if recorded by the module instance
+const TIMELINE_METRICS: &[Metric] = &[
+	Metric::Occupancy,
+	Metric::Stall,
+	Metric::Idle,
+	Metric::Activity,
+	Metric::Bandwidth,
+	Metric::AverageChannelDepth,
+	Metric::MaximumChannelDepth,
+];
+
+/// Sampled metrics of one module instance during one kernel invocation
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModuleInstanceTimeline
+{
+	pub memory_type: &'static str,
+	pub source_files: Vec<FileReference>,
+	pub kernel: String,
+	pub module_instance: String,
+	pub start_time: u64,
+	pub end_time: u64,
+	/// Metrics recorded by the module instance, in the order of the interval values
+	pub metrics: Vec<Metric>,
+	pub intervals: Vec<SampleInterval>,
+}
+
+/// Times in ns
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SampleInterval
+{
+	pub start_time: u64,
+	pub end_time: u64,
+	pub cycles: Option<u64>,
+	pub values: Vec<f32>,
+}
+
+/// Timelines of the module instances originating from the source location, in the order of the
+/// kernel invocations in the profile
+pub fn timelines(profile: &Profile, settings: &Settings, source: &SourcePattern)
+	-> Vec<ModuleInstanceTimeline>
+{
+	profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel))
+		.flat_map(|kernel|
+			kernel.children.iter()
+				.filter_map(|child|
+					match child
+					{
+						Child::ModuleInstance(module_instance) => Some(module_instance),
+						_ => None
+					})
+				.filter(|module_instance| source.matches(&module_instance.source_files))
+				.filter_map(move |module_instance| timeline(kernel, module_instance)))
+		.collect()
+}
+
+/// Module instances of unknown types have no timeline
+fn timeline(kernel: &Kernel, module_instance: &ModuleInstance) -> Option<ModuleInstanceTimeline>
+{
+	let module_instance_details = &module_instance.module_instance_details;
+
+	let memory_type = match module_instance_details
+	{
+		ModuleInstanceDetails::Global(_) => "global",
+		ModuleInstanceDetails::Local(_) => "local",
+		ModuleInstanceDetails::Channel(_) => "channel",
+		ModuleInstanceDetails::Loop(_) => "loop",
+		ModuleInstanceDetails::Unknown(_) => return None,
+	};
+
+	let (metrics, series): (Vec<_>, Vec<_>) = TIMELINE_METRICS.iter()
+		.filter_map(|&metric|
+			metric_series(kernel, module_instance_details, metric).map(|series| (metric, series)))
+		.unzip();
+
+	let intervals = intervals(kernel).into_iter()
+		.enumerate()
+		.map(|(index, (start_time, end_time))|
+			SampleInterval
+			{
+				start_time,
+				end_time,
+				cycles: kernel.total_cycles_between_samples.as_ref()
+					.and_then(|cycles| cycles.get(index).copied()),
+				values: series.iter()
+					.map(|series| series.get(index).copied().unwrap_or(f32::NAN))
+					.collect(),
+			})
+		.collect();
+
+	Some(ModuleInstanceTimeline
+	{
+		memory_type,
+		source_files: module_instance.source_files.clone(),
+		kernel: kernel.name.clone(),
+		module_instance: module_instance.name.clone(),
+		start_time: kernel.start_time,
+		end_time: kernel.end_time,
+		metrics,
+		intervals,
+	})
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	#[test]
+	fn timelines_list_the_intervals_of_each_module_instance()
+	{
+		let profile = fixtures::load(fixtures::example());
+
+		let timelines = timelines(&profile, &Settings::default(), &"kernel.cpp:20".parse().unwrap());
+
+		assert_eq!(timelines.iter()
+				.map(|timeline|
+					(timeline.memory_type, timeline.module_instance.as_str(), timeline.metrics.clone()))
+				.collect::<Vec<_>>(),
+			vec![("loop", "loop0", vec![Metric::Occupancy]), ("loop", "loop1", vec![Metric::Occupancy])]);
+		assert_eq!(timelines[0].intervals.iter()
+				.map(|interval|
+					(interval.start_time, interval.end_time, interval.cycles, interval.values.clone()))
+				.collect::<Vec<_>>(),
+			vec![(0, 1000, Some(1000), vec![100.]), (1000, 2000, Some(1000), vec![100.])]);
+
+		let timelines = super::timelines(&profile, &Settings::default(), &"kernel.cpp:10".parse().unwrap());
+
+		assert_eq!(timelines[0].metrics,
+			vec![Metric::Occupancy, Metric::Stall, Metric::Idle, Metric::Activity, Metric::Bandwidth]);
+		assert_eq!(timelines[0].intervals[1].values, vec![100., 50., 25., 100., 1000.]);
+	}
+
+	#[test]
+	fn timelines_pad_missing_samples_and_cycles()
+	{
+		let mut kernel = fixtures::kernel("K", 0, &[1000, 2000], vec![
+			fixtures::module_instance("global", "/src/kernel.cpp", 10, fixtures::details("__global", &[500])),
+		]);
+		kernel.as_object_mut().unwrap().remove("total_cycles_between_samples");
+		let profile = fixtures::load(fixtures::profile(vec![kernel]));
+
+		let source = "kernel.cpp".parse().unwrap();
+		let timeline = &timelines(&profile, &Settings::default(), &source)[0];
+
+		// The percentages need the cycles, the bandwidth does not
+		assert_eq!(timeline.metrics, vec![Metric::Bandwidth]);
+		assert_eq!(timeline.intervals.iter().map(|interval| interval.cycles).collect::<Vec<_>>(),
+			vec![None, None]);
+		assert_eq!(timeline.intervals[0].values, vec![500.]);
+		assert!(timeline.intervals[1].values[0].is_nan());
+
+		let settings = Settings { excluded_kernels: vec![crate::report::kernel_pattern("K").unwrap()],
+			..Default::default() };
+
+		assert!(timelines(&profile, &settings, &source).is_empty());
+	}
+}
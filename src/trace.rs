@@ -0,0 +1,255 @@
+use crate::data_model::*;
+use crate::metrics::Metric;
+use crate::report::Settings;
+use crate::series::*;
+
+/// Track within a device, which maps to a thread in the trace
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Track
+{
+	CommandQueue(u32),
+	/// Autorun kernels have no command queue and run concurrently, hence one track each
+	Autorun(String),
+}
+
+/// Converts the kernel invocations and memory transfers of a profile into the Chrome Trace Event
+/// format, optionally with counter tracks of the sampled bandwidth and occupancy
+pub fn trace(profile: &Profile, settings: &Settings, counters: bool) -> serde_json::Value
+{
+	let mut tracks = std::collections::BTreeMap::new();
+	let mut events = Vec::new();
+
+	let mut track_id = |device_id: u32, track: Track|
+	{
+		let next_id = tracks.len() as u32 + 1;
+
+		*tracks.entry((device_id, track)).or_insert(next_id)
+	};
+
+	let kernels = profile.kernels.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Kernel(kernel) => Some(kernel),
+				_ => None
+			})
+		.filter(|kernel| settings.considers(kernel));
+
+	for kernel in kernels
+	{
+		let placements = placements(kernel);
+
+		for (device_id, track) in placements.iter().cloned()
+		{
+			events.push(serde_json::json!(
+			{
+				"name": kernel.name,
+				"cat": "kernel",
+				"ph": "X",
+				"ts": microseconds(kernel.start_time),
+				"dur": microseconds(kernel.end_time - kernel.start_time),
+				"pid": device_id,
+				"tid": track_id(device_id, track),
+				"args":
+				{
+					"compute_unit": kernel.compute_unit,
+					"num_samples": kernel.num_samples,
+					"is_autorun": kernel.is_autorun,
+				},
+			}));
+		}
+
+		// The samples cover the invocation as a whole, hence they are shown on its lowest device only
+		if counters
+		{
+			events.extend(counter_events(kernel, placements[0].0));
+		}
+	}
+
+	let memory_transfers = profile.memory_transfers.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::MemoryTransfers(memory_transfers) => Some(memory_transfers),
+				_ => None
+			});
+
+	for memory_transfer in memory_transfers
+	{
+		let device_id = memory_transfer.device_id;
+
+		events.push(serde_json::json!(
+		{
+			"name": memory_transfer.type_transfer,
+			"cat": "memory transfer",
+			"ph": "X",
+			"ts": microseconds(memory_transfer.start_time),
+			"dur": microseconds(memory_transfer.end_time - memory_transfer.start_time),
+			"pid": device_id,
+			"tid": track_id(device_id, Track::CommandQueue(memory_transfer.command_queue_id)),
+		}));
+	}
+
+	let devices = tracks.keys()
+		.map(|(device_id, _)| *device_id)
+		.collect::<std::collections::BTreeSet<_>>();
+
+	let process_names = devices.into_iter()
+		.map(|device_id|
+			serde_json::json!(
+			{
+				"name": "process_name",
+				"ph": "M",
+				"pid": device_id,
+				"args": { "name": format!("Device {}", device_id) },
+			}));
+
+	let thread_names = tracks.iter()
+		.map(|((device_id, track), id)|
+			{
+				let name = match track
+				{
+					Track::CommandQueue(command_queue_id) => format!("Command queue {}", command_queue_id),
+					Track::Autorun(kernel_name) => format!("Autorun {}", kernel_name),
+				};
+
+				serde_json::json!(
+				{
+					"name": "thread_name",
+					"ph": "M",
+					"pid": device_id,
+					"tid": id,
+					"args": { "name": name },
+				})
+			});
+
+	let events = process_names
+		.chain(thread_names)
+		.chain(events)
+		.collect::<Vec<_>>();
+
+	serde_json::json!(
+	{
+		"traceEvents": events,
+		"displayTimeUnit": "ns",
+	})
+}
+
+/// Device and track of each device and command queue pair the kernel was invoked on, the ids are
+/// paired by position with the last one of the shorter list repeated, at least one pair on device 0
+fn placements(kernel: &Kernel) -> Vec<(u32, Track)>
+{
+	let device_ids = if kernel.device_ids.is_empty() { &[0][..] } else { &kernel.device_ids[..] };
+
+	if kernel.is_autorun || kernel.command_queue_ids.is_empty()
+	{
+		return device_ids.iter()
+			.map(|&device_id| (device_id, Track::Autorun(kernel.name.clone())))
+			.collect::<std::collections::BTreeSet<_>>()
+			.into_iter().collect();
+	}
+
+	let command_queue_ids = &kernel.command_queue_ids;
+	let at = |ids: &[u32], index: usize| ids[index.min(ids.len() - 1)];
+
+	(0..device_ids.len().max(command_queue_ids.len()))
+		.map(|index| (at(device_ids, index), Track::CommandQueue(at(command_queue_ids, index))))
+		.collect::<std::collections::BTreeSet<_>>()
+		.into_iter().collect()
+}
+
+/// Counter events of the sampled bandwidth and occupancy of each module instance of the kernel
+fn counter_events(kernel: &Kernel, device_id: u32) -> Vec<serde_json::Value>
+{
+	let intervals = intervals(kernel);
+
+	kernel.children.iter()
+		.filter_map(|child|
+			match child
+			{
+				Child::ModuleInstance(module_instance) => Some(module_instance),
+				_ => None
+			})
+		.flat_map(|module_instance|
+			[Metric::Bandwidth, Metric::Occupancy].iter()
+				.filter_map(move |&metric|
+					metric_series(kernel, &module_instance.module_instance_details, metric)
+						.map(|series| (module_instance, metric, series)))
+				.collect::<Vec<_>>())
+		.flat_map(|(module_instance, metric, series)|
+			{
+				let name = format!("{} {} ({}, {})", metric.label(),
+					module_instance.source_files.iter()
+						.map(ToString::to_string)
+						.collect::<Vec<_>>().join(", "),
+					kernel.name, module_instance.name);
+
+				let counter = move |time: u64, value: f32|
+					serde_json::json!(
+					{
+						"name": name,
+						"cat": "counter",
+						"ph": "C",
+						"ts": microseconds(time),
+						"pid": device_id,
+						"args": { metric.name(): value },
+					});
+
+				intervals.iter()
+					.zip(series)
+					.map(|(&(start_time, _), value)| counter(start_time, value))
+					.chain(std::iter::once(counter(kernel.end_time, 0.)))
+					.collect::<Vec<_>>()
+			})
+		.collect()
+}
+
+/// Trace timestamps are in µs, the profile ones in ns
+fn microseconds(time: u64) -> f64
+{
+	time as f64 / 1e3
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+
+	#[test]
+	fn trace_places_each_device_and_command_queue_on_its_own_track()
+	{
+		let mut shared = fixtures::kernel("K", 0, &[1000], vec![]);
+		shared["device_ids"] = serde_json::json!([0, 1]);
+		shared["command_queue_ids"] = serde_json::json!([3, 4]);
+
+		let mut autorun = fixtures::kernel("A", 0, &[2000], vec![]);
+		autorun["device_ids"] = serde_json::json!([1]);
+		autorun["command_queue_ids"] = serde_json::json!([]);
+		autorun["is_autorun"] = serde_json::json!(true);
+
+		let mut profile = fixtures::profile(vec![shared, autorun]);
+		profile["memtransfers"]["nodes"] = serde_json::json!([fixtures::memory_transfer("read", 1, 4, 1000, 1500)]);
+
+		let trace = trace(&fixtures::load(profile), &Settings::default(), false);
+
+		let tracks = |phase: &str| trace["traceEvents"].as_array().unwrap().iter()
+			.filter(|event| event["ph"] == phase && event["name"] != "process_name")
+			.map(|event|
+				(event["pid"].as_u64().unwrap(), event["tid"].as_u64().unwrap(),
+					event["args"]["name"].as_str().unwrap_or_else(|| event["name"].as_str().unwrap()).to_owned()))
+			.collect::<Vec<_>>();
+
+		assert_eq!(tracks("M"), vec![
+			(0, 1, "Command queue 3".to_owned()),
+			(1, 2, "Command queue 4".to_owned()),
+			(1, 3, "Autorun A".to_owned()),
+		]);
+		assert_eq!(tracks("X"), vec![
+			(0, 1, "K".to_owned()),
+			(1, 2, "K".to_owned()),
+			(1, 3, "A".to_owned()),
+			(1, 2, "read".to_owned()),
+		]);
+	}
+}
@@ -0,0 +1,368 @@
+use intel_fpga_profile_viewer::data_model::FileReference;
+use intel_fpga_profile_viewer::metrics::*;
+use intel_fpga_profile_viewer::report::*;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Wrap};
+
+/// Metrics shown as columns of the tree, which it can be sorted by
+const COLUMNS: &[Metric] = &[Metric::Occupancy, Metric::Stall, Metric::Bandwidth];
+
+struct TreeNode
+{
+	id: usize,
+	label: String,
+	/// Metrics as in the text report, empty for the memory types
+	details: String,
+	values: Vec<Option<f32>>,
+	children: Vec<TreeNode>,
+}
+
+struct App
+{
+	roots: Vec<TreeNode>,
+	expanded: std::collections::HashSet<usize>,
+	sort: Option<Metric>,
+	ascending: bool,
+	query: String,
+	searching: bool,
+	state: TableState,
+}
+
+struct VisibleRow<'a>
+{
+	node: &'a TreeNode,
+	depth: usize,
+	expanded: bool,
+}
+
+/// Shows an expanded report as navigable tree of memory types, source locations, kernels and
+/// unroll instances until the user quits
+pub fn run(report: &Report) -> anyhow::Result<()>
+{
+	let mut id = 0;
+
+	let roots = vec![
+		section(&mut id, "Global memory", &report.global_memory),
+		section(&mut id, "Local memory", &report.local_memory),
+		section(&mut id, "Channel", &report.channel),
+		section(&mut id, "Loop", &report.loops),
+	];
+
+	let mut app = App
+	{
+		expanded: roots.iter().map(|root| root.id).collect(),
+		roots,
+		sort: None,
+		ascending: false,
+		query: String::new(),
+		searching: false,
+		state: TableState::default().with_selected(0),
+	};
+
+	let mut terminal = ratatui::try_init()?;
+	let result = app.run(&mut terminal);
+
+	ratatui::try_restore()?;
+
+	result
+}
+
+fn section<Metrics>(id: &mut usize, label: &str, groups: &[ModuleInstanceGroup<Metrics>]) -> TreeNode
+where
+	Metrics: MetricValues + std::fmt::Display,
+{
+	let children = groups.iter()
+		.map(|group|
+			{
+				let kernels = group.kernels.iter().flatten()
+					.map(|kernel|
+						{
+							let instances = if kernel.instances.len() > 1
+							{
+								kernel.instances.iter().enumerate()
+									.map(|(instance_id, metrics)|
+										node(id, format!("Instance {}", instance_id + 1), metrics, Vec::new()))
+									.collect()
+							}
+							else
+							{
+								Vec::new()
+							};
+
+							node(id, format!("Kernel {}", kernel.name), &kernel.metrics, instances)
+						})
+					.collect();
+
+				node(id, format_location(&group.source_files), &group.metrics, kernels)
+			})
+		.collect();
+
+	*id += 1;
+
+	TreeNode
+	{
+		id: *id,
+		label: label.to_owned(),
+		details: format!("{} source locations", groups.len()),
+		values: vec![None; Metric::ALL.len()],
+		children,
+	}
+}
+
+fn node<Metrics>(id: &mut usize, label: String, metrics: &Metrics, children: Vec<TreeNode>) -> TreeNode
+where
+	Metrics: MetricValues + std::fmt::Display,
+{
+	*id += 1;
+
+	TreeNode
+	{
+		id: *id,
+		label,
+		details: metrics.to_string(),
+		values: Metric::ALL.iter().map(|&metric| metrics.value(metric)).collect(),
+		children,
+	}
+}
+
+/// Single line location with the callsite chain, e.g. `kernel.cpp:42:5 ⮤ helper.hpp:7`
+fn format_location(file_references: &[FileReference]) -> String
+{
+	file_references.iter()
+		.map(|file_reference|
+			std::iter::once(file_reference.to_string())
+				.chain((!file_reference.callsite.is_empty())
+					.then(|| format_location(&file_reference.callsite)))
+				.collect::<Vec<_>>().join(" ⮤ "))
+		.collect::<Vec<_>>().join(", ")
+}
+
+fn value(node: &TreeNode, metric: Metric) -> Option<f32>
+{
+	node.values[Metric::ALL.iter().position(|&other| other == metric).unwrap()]
+}
+
+impl App
+{
+	fn run(&mut self, terminal: &mut ratatui::DefaultTerminal) -> anyhow::Result<()>
+	{
+		loop
+		{
+			terminal.draw(|frame| self.draw(frame))?;
+
+			if let Event::Key(key) = event::read()?
+			{
+				if key.kind != KeyEventKind::Press
+				{
+					continue;
+				}
+
+				if self.searching
+				{
+					match key.code
+					{
+						KeyCode::Enter => self.searching = false,
+						KeyCode::Esc =>
+						{
+							self.searching = false;
+							self.query.clear();
+						}
+						KeyCode::Backspace => { self.query.pop(); }
+						KeyCode::Char(character) => self.query.push(character),
+						_ => {}
+					}
+
+					self.state.select(Some(0));
+
+					continue;
+				}
+
+				match key.code
+				{
+					KeyCode::Char('q') => return Ok(()),
+					KeyCode::Esc if self.query.is_empty() => return Ok(()),
+					KeyCode::Esc => self.query.clear(),
+					KeyCode::Char('/') => self.searching = true,
+					KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+					KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+					KeyCode::PageUp => self.state.scroll_up_by(10),
+					KeyCode::PageDown => self.state.scroll_down_by(10),
+					KeyCode::Home => self.state.select_first(),
+					KeyCode::End => self.state.select_last(),
+					KeyCode::Enter | KeyCode::Char(' ') => self.toggle(None),
+					KeyCode::Right | KeyCode::Char('l') => self.toggle(Some(true)),
+					KeyCode::Left | KeyCode::Char('h') => self.toggle(Some(false)),
+					KeyCode::Char('s') =>
+					{
+						self.sort = match self.sort
+						{
+							None => COLUMNS.first().copied(),
+							Some(metric) => COLUMNS.iter()
+								.skip_while(|&&column| column != metric)
+								.nth(1)
+								.copied(),
+						};
+					}
+					KeyCode::Char('r') => self.ascending = !self.ascending,
+					_ => {}
+				}
+			}
+		}
+	}
+
+	/// Expands or collapses the selected node, toggles it without a target state
+	fn toggle(&mut self, expand: Option<bool>)
+	{
+		let rows = self.visible_rows();
+
+		if let Some(row) = self.state.selected().and_then(|selected| rows.get(selected))
+		{
+			let id = row.node.id;
+			let expand = expand.unwrap_or(!row.expanded);
+
+			if expand && !row.node.children.is_empty()
+			{
+				self.expanded.insert(id);
+			}
+			else
+			{
+				self.expanded.remove(&id);
+			}
+		}
+	}
+
+	fn visible_rows(&self) -> Vec<VisibleRow<'_>>
+	{
+		let mut rows = Vec::new();
+
+		for root in self.sorted(&self.roots)
+		{
+			self.collect_rows(root, 0, &mut rows);
+		}
+
+		rows
+	}
+
+	fn collect_rows<'a>(&'a self, node: &'a TreeNode, depth: usize, rows: &mut Vec<VisibleRow<'a>>)
+	{
+		if !self.matches(node)
+		{
+			return;
+		}
+
+		// While searching, every node with matching descendants is expanded
+		let expanded = !node.children.is_empty()
+			&& (self.expanded.contains(&node.id)
+				|| (!self.query.is_empty() && node.children.iter().any(|child| self.matches(child))));
+
+		rows.push(VisibleRow { node, depth, expanded });
+
+		if expanded
+		{
+			for child in self.sorted(&node.children)
+			{
+				self.collect_rows(child, depth + 1, rows);
+			}
+		}
+	}
+
+	fn matches(&self, node: &TreeNode) -> bool
+	{
+		self.query.is_empty()
+			|| node.label.to_lowercase().contains(&self.query.to_lowercase())
+			|| node.children.iter().any(|child| self.matches(child))
+	}
+
+	fn sorted<'a>(&self, nodes: &'a [TreeNode]) -> Vec<&'a TreeNode>
+	{
+		let mut nodes = nodes.iter().collect::<Vec<_>>();
+
+		if let Some(metric) = self.sort
+		{
+			nodes.sort_by(|left, right|
+				compare_values(value(left, metric), value(right, metric), self.ascending));
+		}
+
+		nodes
+	}
+
+	fn draw(&mut self, frame: &mut ratatui::Frame)
+	{
+		let [main_area, status_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+			.areas(frame.area());
+		let [tree_area, details_area] =
+			Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+				.areas(main_area);
+
+		let rows = self.visible_rows();
+
+		let header = std::iter::once("Module instance".to_owned())
+			.chain(COLUMNS.iter()
+				.map(|&metric|
+					{
+						let direction = match (self.sort == Some(metric), self.ascending)
+						{
+							(false, _) => "",
+							(true, true) => " ▲",
+							(true, false) => " ▼",
+						};
+
+						format!("{}{}{}", metric.label(), metric.unit(), direction)
+					}));
+
+		let table_rows = rows.iter()
+			.map(|row|
+				{
+					let marker = match (row.node.children.is_empty(), row.expanded)
+					{
+						(true, _) => "  ",
+						(false, true) => "▾ ",
+						(false, false) => "▸ ",
+					};
+
+					std::iter::once(format!("{}{}{}", "  ".repeat(row.depth), marker, row.node.label))
+						.chain(COLUMNS.iter()
+							.map(|&metric|
+								value(row.node, metric)
+									.map(|value| format!("{:.2}", value))
+									.unwrap_or_default()))
+						.collect::<Row>()
+				});
+
+		let table = Table::new(table_rows,
+				std::iter::once(Constraint::Min(30))
+					.chain(COLUMNS.iter().map(|_| Constraint::Length(18))))
+			.header(header.collect::<Row>().style(Style::new().add_modifier(Modifier::BOLD)))
+			.row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+			.block(Block::new().borders(Borders::ALL).title("Module instances"));
+
+		let details = self.state.selected()
+			.and_then(|selected| rows.get(selected))
+			.map(|row| format!("{}\n\n{}", row.node.label, row.node.details))
+			.unwrap_or_default();
+
+		let status = if self.searching || !self.query.is_empty()
+		{
+			format!("/{}", self.query)
+		}
+		else
+		{
+			"↑↓ navigate  ←→ collapse/expand  s sort  r reverse  / search  q quit".to_owned()
+		};
+
+		let mut state = self.state.clone();
+
+		frame.render_stateful_widget(table, tree_area, &mut state);
+		frame.render_widget(Paragraph::new(details)
+				.wrap(Wrap { trim: false })
+				.block(Block::new().borders(Borders::ALL).title("Metrics")),
+			details_area);
+		frame.render_widget(Paragraph::new(status), status_area);
+
+		// Rendering clamps the selection and scrolls it into view
+		self.state = state;
+	}
+}
@@ -0,0 +1,363 @@
+use crate::data_model::*;
+use crate::module_instance_details::*;
+
+/// Inconsistency within a profile, which the aggregation would otherwise silently turn into
+/// distorted metrics, e.g. by truncating sample vectors of different lengths
+#[derive(Debug, Clone)]
+pub struct Issue
+{
+	pub subject: String,
+	pub reason: String,
+}
+
+impl std::fmt::Display for Issue
+{
+	fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result
+	{
+		write!(formatter, "{}: {}", self.subject, self.reason)
+	}
+}
+
+/// Checks the samples of every kernel invocation against its sampling intervals, and the
+/// references between the nodes
+pub fn validate(profile: &Profile) -> Vec<Issue>
+{
+	let mut issues = Vec::new();
+
+	let global_memory_names = profile.boards.nodes.iter()
+		.filter_map(|node|
+			match node
+			{
+				Node::Board(board) => Some(board),
+				_ => None
+			})
+		.flat_map(|board| board.children.iter())
+		.filter_map(|child|
+			match child
+			{
+				Child::GlobalMemory(global_memory) => Some(global_memory.global_memory_name.as_str()),
+				_ => None
+			})
+		.collect::<std::collections::BTreeSet<_>>();
+
+	if global_memory_names.is_empty()
+	{
+		issues.push(Issue
+		{
+			subject: "profile".to_owned(),
+			reason: "no board with a global memory".to_owned(),
+		});
+	}
+
+	for node in profile.kernels.nodes.iter()
+	{
+		if let Node::Kernel(kernel) = node
+		{
+			validate_kernel(kernel, &global_memory_names, &mut issues);
+		}
+	}
+
+	for node in profile.memory_transfers.nodes.iter()
+	{
+		if let Node::MemoryTransfers(memory_transfers) = node
+		{
+			if memory_transfers.start_time > memory_transfers.end_time
+			{
+				issues.push(Issue
+				{
+					subject: format!("memory transfer {} (device: {}, command queue: {}, start: {})",
+						memory_transfers.type_transfer, memory_transfers.device_id,
+						memory_transfers.command_queue_id, memory_transfers.start_time),
+					reason: format!("ends before it starts (end: {})", memory_transfers.end_time),
+				});
+			}
+		}
+	}
+
+	issues
+}
+
+fn validate_kernel(kernel: &Kernel, global_memory_names: &std::collections::BTreeSet<&str>,
+	issues: &mut Vec<Issue>)
+{
+	let subject = format!("kernel {} (start: {})", kernel.name, kernel.start_time);
+	let number_of_samples = kernel.sample_timestamps.len();
+
+	let mut report = |subject: &str, reason: String|
+		issues.push(Issue { subject: subject.to_owned(), reason });
+
+	if kernel.start_time > kernel.end_time
+	{
+		report(&subject, format!("ends before it starts (end: {})", kernel.end_time));
+	}
+
+	if kernel.num_samples as usize != number_of_samples
+	{
+		report(&subject, format!("num_samples is {}, but there are {} sample_timestamps",
+			kernel.num_samples, number_of_samples));
+	}
+
+	if let Some(index) = kernel.sample_timestamps.windows(2).position(|pair| pair[0] > pair[1])
+	{
+		report(&subject, format!("sample_timestamps decrease after sample {}", index + 1));
+	}
+
+	let outside = kernel.sample_timestamps.iter()
+		.filter(|&&timestamp| timestamp < kernel.start_time || timestamp > kernel.end_time)
+		.count();
+
+	if outside > 0
+	{
+		report(&subject, format!("{} of {} sample_timestamps outside of the runtime ({} to {})",
+			outside, number_of_samples, kernel.start_time, kernel.end_time));
+	}
+
+	let cycles = match &kernel.total_cycles_between_samples
+	{
+		Some(cycles) =>
+		{
+			check_length(&subject, "total_cycles_between_samples", cycles.len(), number_of_samples,
+				&mut report);
+
+			cycles.as_slice()
+		}
+		None =>
+		{
+			report(&subject, "total_cycles_between_samples missing, the occupancy, stall, idle and \
+				activity cannot be computed".to_owned());
+
+			&[]
+		}
+	};
+
+	for child in kernel.children.iter()
+	{
+		match child
+		{
+			Child::ModuleInstance(module_instance) =>
+			{
+				let subject = format!("module instance {} of {} at {}", module_instance.name, subject,
+					module_instance.source_files.iter()
+						.map(|file_reference| file_reference.to_string())
+						.collect::<Vec<_>>().join(", "));
+
+				for (field, length) in sample_lengths(&module_instance.module_instance_details)
+				{
+					check_length(&subject, field, length, number_of_samples, &mut report);
+				}
+
+				for (field, samples) in cycle_counters(&module_instance.module_instance_details)
+				{
+					let exceeding = samples.iter()
+						.zip(cycles.iter())
+						.filter(|(sample, cycles)| sample > cycles)
+						.count();
+
+					if exceeding > 0
+					{
+						report(&subject, format!("{} of {} {} exceed the cycles of their interval",
+							exceeding, samples.len(), field));
+					}
+				}
+
+				if let ModuleInstanceDetails::Global(global) = &module_instance.module_instance_details
+				{
+					if !global_memory_names.contains(global.global_memory_name.as_str())
+					{
+						report(&subject, format!("global_mem_name {} not found on any board",
+							global.global_memory_name));
+					}
+
+					let invalid = global.bandwidth_effective_samples.iter()
+						.filter(|efficiency| !(0. ..=1.).contains(*efficiency))
+						.count();
+
+					if invalid > 0
+					{
+						report(&subject, format!("{} of {} bandwidth_eff_samples outside of 0 to 1",
+							invalid, global.bandwidth_effective_samples.len()));
+					}
+				}
+			}
+			Child::ExternalMemory(external_memory) =>
+			{
+				let subject = format!("external memory {} (port: {}) of {}", external_memory.name,
+					external_memory.port, subject);
+
+				for (field, length) in [
+					("global_used_bw", external_memory.global_used_bandwidth.len()),
+					("avg_write_burst", external_memory.average_write_burst.len()),
+					("avg_read_burst", external_memory.average_read_burst.len()),
+				].iter().copied()
+				{
+					check_length(&subject, field, length, number_of_samples, &mut report);
+				}
+			}
+			Child::GlobalMemory(_) | Child::Unknown(_) => (),
+		}
+	}
+}
+
+fn check_length<Report: FnMut(&str, String)>(subject: &str, field: &str, length: usize,
+	number_of_samples: usize, report: &mut Report)
+{
+	if length != number_of_samples
+	{
+		report(subject, format!("{} {} for {} sample_timestamps", length, field, number_of_samples));
+	}
+}
+
+/// Lengths of the sample vectors by their name in the profile, the optional cache hits only if
+/// present
+fn sample_lengths(module_instance_details: &ModuleInstanceDetails) -> Vec<(&'static str, usize)>
+{
+	let mut lengths = Vec::new();
+
+	match module_instance_details
+	{
+		ModuleInstanceDetails::Global(global) =>
+		{
+			lengths.extend([
+				("bandwidth_samples", global.bandwidth_samples.len()),
+				("bandwidth_eff_samples", global.bandwidth_effective_samples.len()),
+				("average_burst_size", global.average_burst_size.len()),
+			].iter().copied());
+
+			if !global.cache_hit_samples.is_empty()
+			{
+				lengths.push(("cache_hit_samples", global.cache_hit_samples.len()));
+			}
+		}
+		ModuleInstanceDetails::Channel(channel) =>
+		{
+			lengths.extend([
+				("bandwidth_samples", channel.bandwidth_samples.len()),
+				("average_channel_depth_samples", channel.average_channel_depth_samples.len()),
+				("max_channel_depth_samples", channel.maximum_channel_depth_samples.len()),
+			].iter().copied());
+		}
+		ModuleInstanceDetails::Local(_) | ModuleInstanceDetails::Loop(_)
+			| ModuleInstanceDetails::Unknown(_) => (),
+	}
+
+	lengths.extend(cycle_counters(module_instance_details).into_iter()
+		.map(|(field, samples)| (field, samples.len())));
+
+	lengths
+}
+
+/// Samples that count cycles within the sampling interval, hence cannot exceed its cycles
+fn cycle_counters(module_instance_details: &ModuleInstanceDetails) -> Vec<(&'static str, &[u64])>
+{
+	let stall: Option<&dyn Stall> = match module_instance_details
+	{
+		ModuleInstanceDetails::Global(global) => Some(global),
+		ModuleInstanceDetails::Local(local) => Some(local),
+		ModuleInstanceDetails::Channel(channel) => Some(channel),
+		ModuleInstanceDetails::Loop(_) | ModuleInstanceDetails::Unknown(_) => None,
+	};
+
+	let occupancy: &dyn Occupancy = match module_instance_details
+	{
+		ModuleInstanceDetails::Global(global) => global,
+		ModuleInstanceDetails::Local(local) => local,
+		ModuleInstanceDetails::Channel(channel) => channel,
+		ModuleInstanceDetails::Loop(loop_) => loop_,
+		ModuleInstanceDetails::Unknown(_) => return Vec::new(),
+	};
+
+	std::iter::once(("occupancy_samples", occupancy.occupancy_samples()))
+		.chain(stall.into_iter()
+			.flat_map(|stall|
+				vec![
+					("stall_samples", stall.stall_samples()),
+					("idle_samples", stall.idle_samples()),
+					("activity_samples", stall.activity_samples()),
+				]))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::fixtures;
+	use serde_json::json;
+
+	fn issues(profile: serde_json::Value) -> Vec<String>
+	{
+		validate(&fixtures::load(profile)).iter().map(|issue| issue.to_string()).collect()
+	}
+
+	fn board() -> serde_json::Value
+	{
+		json!({
+			"type": "board",
+			"board_type": "a10",
+			"children": [{
+				"type": "globalmem",
+				"global_memory_name": "DDR",
+				"max_theoretical_globalmem_bw": 19200,
+				"max_burst_count": 16,
+			}],
+		})
+	}
+
+	#[test]
+	fn validate_accepts_a_consistent_profile()
+	{
+		let mut profile = fixtures::example();
+		profile["boards"]["nodes"] = json!([board()]);
+
+		assert_eq!(issues(profile), Vec::<String>::new());
+	}
+
+	#[test]
+	fn validate_reports_the_inconsistent_samples_and_timestamps()
+	{
+		let mut global = fixtures::details("__global", &[500, 400, 100]);
+		global["stall_samples"] = json!([250, 200]);
+		global["global_mem_name"] = json!("HBM");
+		global["bandwidth_eff_samples"] = json!([0.5, 1.5, 0.1]);
+
+		// The second interval ends before it starts, hence has no cycles
+		let mut first = fixtures::kernel("K", 0, &[1000, 500, 3000],
+			vec![fixtures::module_instance("global", "/src/kernel.cpp", 10, global)]);
+		first["end_time"] = json!(2000);
+		first["num_samples"] = json!(4);
+
+		let loop_ = fixtures::module_instance("loop", "/src/kernel.cpp", 20,
+			fixtures::details("__loop", &[100]));
+		let mut second = fixtures::kernel("L", 5000, &[6000, 7000], vec![loop_]);
+		second.as_object_mut().unwrap().remove("total_cycles_between_samples");
+
+		let mut profile = fixtures::profile(vec![first, second]);
+		profile["memtransfers"]["nodes"] = json!([fixtures::memory_transfer("h2d", 0, 1, 200, 100)]);
+
+		assert_eq!(issues(profile), vec![
+			"profile: no board with a global memory",
+			"kernel K (start: 0): num_samples is 4, but there are 3 sample_timestamps",
+			"kernel K (start: 0): sample_timestamps decrease after sample 1",
+			"kernel K (start: 0): 1 of 3 sample_timestamps outside of the runtime (0 to 2000)",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 2 stall_samples for 3 \
+				sample_timestamps",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 1 of 3 occupancy_samples \
+				exceed the cycles of their interval",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 1 of 2 stall_samples exceed \
+				the cycles of their interval",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 1 of 3 idle_samples exceed \
+				the cycles of their interval",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 1 of 3 activity_samples \
+				exceed the cycles of their interval",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: global_mem_name HBM not \
+				found on any board",
+			"module instance global of kernel K (start: 0) at /src/kernel.cpp:10: 1 of 3 bandwidth_eff_samples \
+				outside of 0 to 1",
+			"kernel L (start: 5000): total_cycles_between_samples missing, the occupancy, stall, idle and \
+				activity cannot be computed",
+			"module instance loop of kernel L (start: 5000) at /src/kernel.cpp:20: 1 occupancy_samples for 2 \
+				sample_timestamps",
+			"memory transfer h2d (device: 0, command queue: 1, start: 200): ends before it starts (end: 100)",
+		]);
+	}
+}